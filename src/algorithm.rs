@@ -1,21 +1,38 @@
+use std::cmp::Ordering;
 use std::fmt;
 
 use crate::errors::Error;
+use crate::registry::{self, CustomAlgorithm, HashAlgorithm};
 
 /**
 Valid algorithms for integrity strings.
 
+Algorithms are ordered from strongest to weakest, according to their
+[`HashAlgorithm::strength`](trait.HashAlgorithm.html#tymethod.strength). The
+SHA-3 variants are ordered right after their SHA-2 counterparts of the same
+output size, so `Sha512` is still preferred over `Sha3_512` when picking an
+algorithm.
+
 `Sha1` and `Xxh3` are special cases in this library--they're not allowed by the
 current SRI spec, but they're useful enough that having first-class support
 makes sense. They should also be completely harmless to have in your strings
 if you do use it in a browser context--they just won't be used.
 */
 #[non_exhaustive]
-#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum Algorithm {
     Sha512,
+    Sha3_512,
     Sha384,
+    Sha3_384,
     Sha256,
+    Sha3_256,
+    /// BLAKE3 is a fast cryptographic hash function, well suited to hashing
+    /// very large inputs. `ssri` uses its default 256-bit output.
+    ///
+    /// Only available when the `blake3` feature is enabled.
+    #[cfg(feature = "blake3")]
+    Blake3,
     Sha1,
     /// xxh3 is a non-cryptographic hash function that is very fast and can be
     /// used to speed up integrity calculations, at the cost of
@@ -24,11 +41,84 @@ pub enum Algorithm {
     /// `ssri` uses 128-bit xxh3 hashes, which have been shown to have no
     /// conflicts even on billions of hashes.
     Xxh3,
+    /// A user-defined algorithm, added through
+    /// [`register_algorithm`](fn.register_algorithm.html).
+    Custom(CustomAlgorithm),
+    /// An algorithm `ssri` doesn't know about, kept around by
+    /// [`Integrity::parse_lenient`](struct.Integrity.html#method.parse_lenient).
+    ///
+    /// Unknown algorithms always sort after every known one, and can't be used
+    /// to generate or check data.
+    Unknown(UnknownAlgorithm),
+}
+
+impl Algorithm {
+    /// Number of bytes in a digest produced by this algorithm, or `0` for
+    /// [`Algorithm::Unknown`].
+    pub fn digest_len(&self) -> usize {
+        self.implementation().map_or(0, |i| i.digest_len())
+    }
+
+    /// Relative strength of this algorithm. See
+    /// [`HashAlgorithm`](trait.HashAlgorithm.html) for the built-in ranks.
+    /// [`Algorithm::Unknown`] has a strength of `0`.
+    pub fn strength(&self) -> u32 {
+        self.implementation().map_or(0, |i| i.strength())
+    }
+
+    /// Whether this algorithm is meant to resist deliberate collisions. See
+    /// [`HashAlgorithm::is_cryptographic`](trait.HashAlgorithm.html#method.is_cryptographic).
+    /// [`Algorithm::Unknown`] is never considered cryptographic.
+    pub fn is_cryptographic(&self) -> bool {
+        self.implementation()
+            .map_or(false, |i| i.is_cryptographic())
+    }
+
+    fn name(&self) -> &str {
+        match self {
+            Algorithm::Unknown(unknown) => unknown.as_str(),
+            _ => self.implementation().map_or("", |i| i.name()),
+        }
+    }
+
+    pub(crate) fn implementation(&self) -> Option<&'static dyn HashAlgorithm> {
+        Some(match self {
+            Algorithm::Sha512 => &registry::SHA512,
+            Algorithm::Sha3_512 => &registry::SHA3_512,
+            Algorithm::Sha384 => &registry::SHA384,
+            Algorithm::Sha3_384 => &registry::SHA3_384,
+            Algorithm::Sha256 => &registry::SHA256,
+            Algorithm::Sha3_256 => &registry::SHA3_256,
+            #[cfg(feature = "blake3")]
+            Algorithm::Blake3 => &registry::BLAKE3,
+            Algorithm::Sha1 => &registry::SHA1,
+            Algorithm::Xxh3 => &registry::XXH3,
+            Algorithm::Custom(custom) => custom.implementation(),
+            Algorithm::Unknown(_) => return None,
+        })
+    }
+}
+
+impl PartialOrd for Algorithm {
+    fn partial_cmp(&self, other: &Algorithm) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Algorithm {
+    fn cmp(&self, other: &Algorithm) -> Ordering {
+        let self_unknown = matches!(self, Algorithm::Unknown(_));
+        let other_unknown = matches!(other, Algorithm::Unknown(_));
+        self_unknown
+            .cmp(&other_unknown)
+            .then_with(|| other.strength().cmp(&self.strength()))
+            .then_with(|| self.name().cmp(other.name()))
+    }
 }
 
 impl fmt::Display for Algorithm {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}", format!("{:?}", self).to_lowercase())
+        f.write_str(self.name())
     }
 }
 
@@ -36,14 +126,57 @@ impl std::str::FromStr for Algorithm {
     type Err = Error;
 
     fn from_str(s: &str) -> Result<Algorithm, Self::Err> {
-        match s {
-            "sha1" => Ok(Algorithm::Sha1),
-            "sha256" => Ok(Algorithm::Sha256),
-            "sha384" => Ok(Algorithm::Sha384),
-            "sha512" => Ok(Algorithm::Sha512),
-            "xxh3" => Ok(Algorithm::Xxh3),
-            _ => Err(Error::ParseIntegrityError(s.into())),
+        registry::lookup(s).ok_or_else(|| Error::ParseIntegrityError(s.into()))
+    }
+}
+
+/**
+Name of an algorithm that isn't built in or registered, wrapped by
+[`Algorithm::Unknown`](enum.Algorithm.html#variant.Unknown).
+
+The name is stored inline so `Algorithm` stays `Copy`, which limits it to
+[`UnknownAlgorithm::MAX_LEN`] bytes.
+*/
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+pub struct UnknownAlgorithm {
+    len: u8,
+    name: [u8; UnknownAlgorithm::MAX_LEN],
+}
+
+impl UnknownAlgorithm {
+    /// Longest algorithm name that can be kept as an `UnknownAlgorithm`.
+    pub const MAX_LEN: usize = 30;
+
+    /// Wraps `name`, as long as it's a plausible algorithm name that fits.
+    pub(crate) fn new(name: &str) -> Option<UnknownAlgorithm> {
+        if name.is_empty()
+            || name.len() > Self::MAX_LEN
+            || !name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            return None;
         }
+        let mut bytes = [0; Self::MAX_LEN];
+        bytes[..name.len()].copy_from_slice(name.as_bytes());
+        Some(UnknownAlgorithm {
+            len: name.len() as u8,
+            name: bytes,
+        })
+    }
+
+    /// The algorithm name, as it appeared in the source string.
+    pub fn as_str(&self) -> &str {
+        // Only ever built from a `&str` of ASCII characters.
+        std::str::from_utf8(&self.name[..self.len as usize]).unwrap_or_default()
+    }
+}
+
+impl fmt::Debug for UnknownAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("UnknownAlgorithm")
+            .field(&self.as_str())
+            .finish()
     }
 }
 
@@ -58,6 +191,16 @@ mod tests {
         assert_eq!(format!("{}", Sha384), "sha384");
         assert_eq!(format!("{}", Sha512), "sha512");
         assert_eq!(format!("{}", Xxh3), "xxh3");
+        assert_eq!(format!("{}", Sha3_256), "sha3-256");
+        assert_eq!(format!("{}", Sha3_384), "sha3-384");
+        assert_eq!(format!("{}", Sha3_512), "sha3-512");
+    }
+
+    #[test]
+    fn sha3_parsing() {
+        for algo in [Sha3_256, Sha3_384, Sha3_512] {
+            assert_eq!(algo.to_string().parse::<super::Algorithm>().unwrap(), algo);
+        }
     }
 
     #[test]
@@ -66,4 +209,40 @@ mod tests {
         arr.sort_unstable();
         assert_eq!(arr, [Sha512, Sha384, Sha256, Sha1, Xxh3])
     }
+
+    #[test]
+    fn unknown_ordering() {
+        let md5 = Unknown(super::UnknownAlgorithm::new("md5").unwrap());
+        let abc = Unknown(super::UnknownAlgorithm::new("abc").unwrap());
+        assert_eq!(md5.to_string(), "md5");
+        let mut arr = [md5, Xxh3, abc, Sha1, Sha512];
+        arr.sort_unstable();
+        assert_eq!(arr, [Sha512, Sha1, Xxh3, abc, md5]);
+        assert!("md5".parse::<super::Algorithm>().is_err());
+    }
+
+    #[test]
+    fn sha3_ordering() {
+        let mut arr = [Sha3_256, Sha1, Sha512, Sha3_512, Sha256, Sha3_384, Sha384];
+        arr.sort_unstable();
+        assert_eq!(
+            arr,
+            [Sha512, Sha3_512, Sha384, Sha3_384, Sha256, Sha3_256, Sha1]
+        )
+    }
+
+    #[cfg(feature = "blake3")]
+    #[test]
+    fn blake3_formatting() {
+        assert_eq!(format!("{}", Blake3), "blake3");
+        assert_eq!("blake3".parse::<super::Algorithm>().unwrap(), Blake3);
+    }
+
+    #[cfg(feature = "blake3")]
+    #[test]
+    fn blake3_ordering() {
+        let mut arr = [Sha1, Blake3, Sha256, Sha3_256, Sha384, Sha512, Xxh3];
+        arr.sort_unstable();
+        assert_eq!(arr, [Sha512, Sha384, Sha256, Sha3_256, Blake3, Sha1, Xxh3])
+    }
 }
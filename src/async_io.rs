@@ -0,0 +1,649 @@
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+#[cfg(feature = "futures")]
+use futures_core::Stream;
+#[cfg(feature = "tokio")]
+use tokio::io::ReadBuf;
+
+use crate::algorithm::Algorithm;
+use crate::errors::Error;
+use crate::integrity::Integrity;
+use crate::opts::IntegrityOpts;
+use crate::reader::invalid_data;
+use crate::stream::StreamChecker;
+
+/**
+Wraps an async reader, generating an [`Integrity`](struct.Integrity.html) for
+everything read through it, and optionally checking it against an expected
+one.
+
+If the data doesn't match, the read that hits EOF fails with an
+[`io::Error`](std::io::Error) of kind `InvalidData` that wraps an
+[`Error::IntegrityCheckError`](enum.Error.html).
+
+With the `tokio` feature, this wraps and implements tokio's `AsyncRead`. With
+the `futures` feature, it wraps and implements `futures-io`'s `AsyncRead`.
+
+# Examples
+
+```
+# #[cfg(feature = "tokio")] {
+# use ssri::{Algorithm, AsyncIntegrityReader, Integrity, IntegrityOpts};
+# tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(async {
+let data = b"hello world";
+let opts = IntegrityOpts::new().algorithm(Algorithm::Sha1);
+let mut reader = AsyncIntegrityReader::new(&data[..], opts).expected(Integrity::from(data));
+tokio::io::copy(&mut reader, &mut tokio::io::sink()).await.unwrap();
+assert_eq!(reader.algorithm(), Some(Algorithm::Sha256));
+assert_eq!(reader.integrity().unwrap().hashes.len(), 2);
+# });
+# }
+```
+*/
+#[derive(Debug)]
+pub struct AsyncIntegrityReader<R> {
+    inner: R,
+    checker: StreamChecker,
+}
+
+impl<R> AsyncIntegrityReader<R> {
+    /// Creates a new `AsyncIntegrityReader` that hashes the data read from
+    /// `inner` with the algorithms configured on `opts`.
+    pub fn new(inner: R, opts: IntegrityOpts) -> AsyncIntegrityReader<R> {
+        AsyncIntegrityReader {
+            inner,
+            checker: StreamChecker::new(opts),
+        }
+    }
+
+    /// Checks the data against `sri` once the inner reader hits EOF. If it's
+    /// not already configured, the algorithm `sri` prefers is added to the
+    /// generated `Integrity`.
+    ///
+    /// Panics if called after the first successful read, even if it was empty.
+    pub fn expected(mut self, sri: Integrity) -> Self {
+        self.checker.expect(sri);
+        self
+    }
+
+    /// Fails with [`Error::TooManyBytes`](enum.Error.html#variant.TooManyBytes)
+    /// as soon as more than `len` bytes are read, and with
+    /// [`Error::TooFewBytes`](enum.Error.html#variant.TooFewBytes) at EOF if
+    /// fewer were.
+    pub fn expected_len(mut self, len: u64) -> Self {
+        self.checker.expect_len(len);
+        self
+    }
+
+    /// Number of bytes read through this reader so far.
+    pub fn bytes_read(&self) -> u64 {
+        self.checker.bytes()
+    }
+
+    /// The `Integrity` generated from the data, once the inner reader has hit
+    /// EOF. This is available even if the check failed.
+    pub fn integrity(&self) -> Option<&Integrity> {
+        self.checker.integrity()
+    }
+
+    /// The algorithm the data was verified with, once the inner reader has hit
+    /// EOF and the check passed.
+    pub fn algorithm(&self) -> Option<Algorithm> {
+        self.checker.algorithm()
+    }
+
+    /// Unwraps this `AsyncIntegrityReader`, returning the inner reader.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl<R: tokio::io::AsyncRead + Unpin> tokio::io::AsyncRead for AsyncIntegrityReader<R> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        if this.checker.is_finished() {
+            return Poll::Ready(this.checker.finish().map_err(invalid_data));
+        }
+        let before = buf.filled().len();
+        match Pin::new(&mut this.inner).poll_read(cx, buf) {
+            Poll::Ready(Ok(())) => {}
+            other => return other,
+        }
+        let read = &buf.filled()[before..];
+        if read.is_empty() && buf.remaining() > 0 {
+            return Poll::Ready(this.checker.finish().map_err(invalid_data));
+        }
+        Poll::Ready(this.checker.input(read).map_err(invalid_data))
+    }
+}
+
+/**
+Wraps an async writer, forwarding everything written to it while generating an
+[`Integrity`](struct.Integrity.html) for the data, and optionally checking it
+against an expected one.
+
+The check happens on shutdown (`poll_close` for `futures-io`): if the data
+doesn't match, it fails with an [`io::Error`](std::io::Error) of kind
+`InvalidData` that wraps an [`Error::IntegrityCheckError`](enum.Error.html),
+after shutting down the inner writer.
+
+With the `tokio` feature, this wraps and implements tokio's `AsyncWrite`. With
+the `futures` feature, it wraps and implements `futures-io`'s `AsyncWrite`.
+
+# Examples
+
+```
+# #[cfg(feature = "tokio")] {
+# use ssri::{AsyncIntegrityWriter, Integrity, IntegrityOpts};
+use tokio::io::AsyncWriteExt;
+# tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(async {
+let mut writer = AsyncIntegrityWriter::new(Vec::new(), IntegrityOpts::new())
+    .expected(Integrity::from(b"hello world"));
+writer.write_all(b"hello world").await.unwrap();
+writer.shutdown().await.unwrap();
+assert_eq!(writer.integrity(), Some(&Integrity::from(b"hello world")));
+# });
+# }
+```
+*/
+#[derive(Debug)]
+pub struct AsyncIntegrityWriter<W> {
+    inner: W,
+    checker: StreamChecker,
+}
+
+impl<W> AsyncIntegrityWriter<W> {
+    /// Creates a new `AsyncIntegrityWriter` that forwards data to `inner`,
+    /// hashing it with the algorithms configured on `opts`.
+    pub fn new(inner: W, opts: IntegrityOpts) -> AsyncIntegrityWriter<W> {
+        AsyncIntegrityWriter {
+            inner,
+            checker: StreamChecker::new(opts),
+        }
+    }
+
+    /// Checks the written data against `sri` on shutdown. If it's not already
+    /// configured, the algorithm `sri` prefers is added to the generated
+    /// `Integrity`.
+    ///
+    /// Panics if called after the first successful write or shutdown.
+    pub fn expected(mut self, sri: Integrity) -> Self {
+        self.checker.expect(sri);
+        self
+    }
+
+    /// Fails with [`Error::TooManyBytes`](enum.Error.html#variant.TooManyBytes)
+    /// instead of writing more than `len` bytes, and with
+    /// [`Error::TooFewBytes`](enum.Error.html#variant.TooFewBytes) on shutdown
+    /// if fewer were written.
+    pub fn expected_len(mut self, len: u64) -> Self {
+        self.checker.expect_len(len);
+        self
+    }
+
+    /// Number of bytes written through this writer so far.
+    pub fn bytes_written(&self) -> u64 {
+        self.checker.bytes()
+    }
+
+    /// The `Integrity` generated from the data, once the writer has been shut
+    /// down. This is available even if the check failed.
+    pub fn integrity(&self) -> Option<&Integrity> {
+        self.checker.integrity()
+    }
+
+    /// Generates the `Integrity` for everything written so far, checking it
+    /// if an expected `Integrity` was provided, and returns it along with the
+    /// inner writer. Use this instead of shutting down to keep using the inner
+    /// writer.
+    pub fn finish(self) -> Result<(Integrity, W), Error> {
+        Ok((self.checker.into_result()?, self.inner))
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl<W: tokio::io::AsyncWrite + Unpin> tokio::io::AsyncWrite for AsyncIntegrityWriter<W> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        let buf = match this.checker.limit(buf) {
+            Ok(buf) => buf,
+            Err(e) => return Poll::Ready(Err(invalid_data(e))),
+        };
+        let written = match Pin::new(&mut this.inner).poll_write(cx, buf) {
+            Poll::Ready(Ok(written)) => written,
+            other => return other,
+        };
+        Poll::Ready(
+            this.checker
+                .input(&buf[..written])
+                .map(|_| written)
+                .map_err(invalid_data),
+        )
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        match Pin::new(&mut this.inner).poll_shutdown(cx) {
+            Poll::Ready(Ok(())) => Poll::Ready(this.checker.finish().map_err(invalid_data)),
+            other => other,
+        }
+    }
+}
+
+#[cfg(feature = "futures")]
+impl<R: futures_io::AsyncRead + Unpin> futures_io::AsyncRead for AsyncIntegrityReader<R> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        if this.checker.is_finished() {
+            return Poll::Ready(this.checker.finish().map(|_| 0).map_err(invalid_data));
+        }
+        let read = match Pin::new(&mut this.inner).poll_read(cx, buf) {
+            Poll::Ready(Ok(read)) => read,
+            other => return other,
+        };
+        if read == 0 && !buf.is_empty() {
+            return Poll::Ready(this.checker.finish().map(|_| 0).map_err(invalid_data));
+        }
+        Poll::Ready(
+            this.checker
+                .input(&buf[..read])
+                .map(|_| read)
+                .map_err(invalid_data),
+        )
+    }
+}
+
+#[cfg(feature = "futures")]
+impl<W: futures_io::AsyncWrite + Unpin> futures_io::AsyncWrite for AsyncIntegrityWriter<W> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        let buf = match this.checker.limit(buf) {
+            Ok(buf) => buf,
+            Err(e) => return Poll::Ready(Err(invalid_data(e))),
+        };
+        let written = match Pin::new(&mut this.inner).poll_write(cx, buf) {
+            Poll::Ready(Ok(written)) => written,
+            other => return other,
+        };
+        Poll::Ready(
+            this.checker
+                .input(&buf[..written])
+                .map(|_| written)
+                .map_err(invalid_data),
+        )
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        match Pin::new(&mut this.inner).poll_close(cx) {
+            Poll::Ready(Ok(())) => Poll::Ready(this.checker.finish().map_err(invalid_data)),
+            other => other,
+        }
+    }
+}
+
+/**
+Wraps a [`Stream`](futures_core::Stream) of byte chunks, such as an HTTP body,
+passing every item through while generating an
+[`Integrity`](struct.Integrity.html) for the data, and optionally checking it
+against an expected one.
+
+Errors from the inner stream are passed through untouched. Once the inner
+stream ends, the data is checked: if it doesn't match, one final `Err` item
+converted from an [`Error::IntegrityCheckError`](enum.Error.html) is yielded
+before the stream ends. The item error type can be anything `Error` converts
+into, including `Error` itself and `io::Error`.
+
+Only available when the `futures` feature is enabled.
+
+# Examples
+
+```
+# use ssri::{Error, Integrity, IntegrityOpts, IntegrityStream};
+use futures::{executor::block_on, stream, StreamExt};
+
+let chunks = vec![Ok::<_, Error>(&b"hello "[..]), Ok(&b"world"[..])];
+let mut body = IntegrityStream::new(stream::iter(chunks), IntegrityOpts::new())
+    .expected(Integrity::from(b"hello world"));
+let data = block_on(async {
+    let mut data = Vec::new();
+    while let Some(chunk) = body.next().await {
+        data.extend_from_slice(chunk?);
+    }
+    Ok::<_, Error>(data)
+})
+.unwrap();
+assert_eq!(data, b"hello world");
+```
+*/
+#[cfg(feature = "futures")]
+#[derive(Debug)]
+pub struct IntegrityStream<S> {
+    inner: S,
+    checker: StreamChecker,
+    done: bool,
+}
+
+#[cfg(feature = "futures")]
+impl<S> IntegrityStream<S> {
+    /// Creates a new `IntegrityStream` that hashes the chunks yielded by
+    /// `inner` with the algorithms configured on `opts`.
+    pub fn new(inner: S, opts: IntegrityOpts) -> IntegrityStream<S> {
+        IntegrityStream {
+            inner,
+            checker: StreamChecker::new(opts),
+            done: false,
+        }
+    }
+
+    /// Checks the data against `sri` once the inner stream ends. If it's not
+    /// already configured, the algorithm `sri` prefers is added to the
+    /// generated `Integrity`.
+    ///
+    /// Panics if called after the stream has yielded a chunk or ended.
+    pub fn expected(mut self, sri: Integrity) -> Self {
+        self.checker.expect(sri);
+        self
+    }
+
+    /// Yields [`Error::TooManyBytes`](enum.Error.html#variant.TooManyBytes)
+    /// as soon as more than `len` bytes have come through, and
+    /// [`Error::TooFewBytes`](enum.Error.html#variant.TooFewBytes) when the
+    /// inner stream ends if fewer did.
+    pub fn expected_len(mut self, len: u64) -> Self {
+        self.checker.expect_len(len);
+        self
+    }
+
+    /// Number of bytes that have passed through this stream so far.
+    pub fn bytes_read(&self) -> u64 {
+        self.checker.bytes()
+    }
+
+    /// The `Integrity` generated from the data, once the inner stream has
+    /// ended. This is available even if the check failed.
+    pub fn integrity(&self) -> Option<&Integrity> {
+        self.checker.integrity()
+    }
+
+    /// The algorithm the data was verified with, once the inner stream has
+    /// ended and the check passed.
+    pub fn algorithm(&self) -> Option<Algorithm> {
+        self.checker.algorithm()
+    }
+
+    /// Unwraps this `IntegrityStream`, returning the inner stream.
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+#[cfg(feature = "futures")]
+impl<S, B, E> Stream for IntegrityStream<S>
+where
+    S: Stream<Item = Result<B, E>> + Unpin,
+    B: AsRef<[u8]>,
+    E: From<Error>,
+{
+    type Item = Result<B, E>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        if this.done {
+            return Poll::Ready(None);
+        }
+        match Pin::new(&mut this.inner).poll_next(cx) {
+            Poll::Ready(Some(Ok(chunk))) => match this.checker.input(chunk.as_ref()) {
+                Ok(()) => Poll::Ready(Some(Ok(chunk))),
+                Err(e) => {
+                    this.done = true;
+                    Poll::Ready(Some(Err(e.into())))
+                }
+            },
+            Poll::Ready(None) => {
+                this.done = true;
+                match this.checker.finish() {
+                    Ok(()) => Poll::Ready(None),
+                    Err(e) => Poll::Ready(Some(Err(e.into()))),
+                }
+            }
+            other => other,
+        }
+    }
+}
+
+#[cfg(all(test, feature = "tokio"))]
+mod tokio_tests {
+    use std::io;
+
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    use super::{AsyncIntegrityReader, AsyncIntegrityWriter};
+    use crate::{Algorithm, Error, Integrity, IntegrityOpts};
+
+    #[tokio::test]
+    async fn read_generate() {
+        let opts = IntegrityOpts::new().algorithm(Algorithm::Sha256);
+        let mut reader = AsyncIntegrityReader::new(&b"hello world"[..], opts);
+        let mut out = Vec::new();
+        reader.read_to_end(&mut out).await.unwrap();
+        assert_eq!(out, b"hello world");
+        assert_eq!(reader.bytes_read(), 11);
+        assert_eq!(reader.integrity(), Some(&Integrity::from(b"hello world")));
+        assert_eq!(reader.algorithm(), None);
+    }
+
+    #[tokio::test]
+    async fn read_mismatch() {
+        let sri = Integrity::from(b"hello world");
+        let mut reader =
+            AsyncIntegrityReader::new(&b"goodbye world"[..], IntegrityOpts::new()).expected(sri);
+        let err = reader.read_to_end(&mut Vec::new()).await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        let err = err
+            .get_ref()
+            .and_then(|e| e.downcast_ref::<Error>())
+            .unwrap();
+        assert!(matches!(err, Error::IntegrityCheckError(..)));
+        assert_eq!(reader.integrity(), Some(&Integrity::from(b"goodbye world")));
+    }
+
+    #[tokio::test]
+    async fn write_verify() {
+        let opts = IntegrityOpts::new().algorithm(Algorithm::Sha1);
+        let mut writer =
+            AsyncIntegrityWriter::new(Vec::new(), opts).expected(Integrity::from(b"hello world"));
+        writer.write_all(b"hello ").await.unwrap();
+        writer.write_all(b"world").await.unwrap();
+        writer.shutdown().await.unwrap();
+        assert_eq!(writer.bytes_written(), 11);
+        let (sri, data) = writer.finish().unwrap();
+        assert_eq!(data, b"hello world");
+        assert_eq!(
+            sri.to_string(),
+            "sha256-uU0nuZNNPgilLlLX2n2r+sSE7+N6U4DukIj3rOLvzek= sha1-Kq5sNclPz7QV2+lfQIuc6R7oRu0="
+        );
+    }
+
+    #[tokio::test]
+    async fn write_mismatch() {
+        let mut writer = AsyncIntegrityWriter::new(Vec::new(), IntegrityOpts::new())
+            .expected(Integrity::from(b"hello world"));
+        writer.write_all(b"goodbye world").await.unwrap();
+        let err = writer.shutdown().await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(writer.finish().is_err());
+    }
+
+    #[tokio::test]
+    async fn write_expected_len() {
+        let mut writer = AsyncIntegrityWriter::new(Vec::new(), IntegrityOpts::new())
+            .expected(Integrity::from(b"hello"))
+            .expected_len(5);
+        let err = writer.write_all(b"hello world").await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(writer.bytes_written(), 5);
+        assert_eq!(writer.finish().unwrap_err(), Error::TooManyBytes(5, 11));
+    }
+
+    #[tokio::test]
+    async fn read_too_short() {
+        let mut reader =
+            AsyncIntegrityReader::new(&b"hello"[..], IntegrityOpts::new()).expected_len(11);
+        let err = reader.read_to_end(&mut Vec::new()).await.unwrap_err();
+        let err = err
+            .get_ref()
+            .and_then(|e| e.downcast_ref::<Error>())
+            .unwrap();
+        assert_eq!(err, &Error::TooFewBytes(11, 5));
+    }
+
+    #[tokio::test]
+    #[should_panic(expected = "input has started")]
+    async fn expected_after_read() {
+        let opts = IntegrityOpts::new().algorithm(Algorithm::Sha256);
+        let mut reader = AsyncIntegrityReader::new(&b""[..], opts);
+        assert_eq!(reader.read(&mut [0; 8]).await.unwrap(), 0);
+        let _ = reader.expected(Integrity::from(b""));
+    }
+
+    #[tokio::test]
+    #[should_panic(expected = "input has started")]
+    async fn expected_after_write() {
+        let opts = IntegrityOpts::new().algorithm(Algorithm::Sha256);
+        let mut writer = AsyncIntegrityWriter::new(Vec::new(), opts);
+        writer.write_all(b"hello").await.unwrap();
+        let _ = writer.expected(Integrity::from(b"hello"));
+    }
+}
+
+#[cfg(all(test, feature = "futures"))]
+mod futures_tests {
+    use std::io;
+
+    use futures::executor::block_on;
+    use futures::io::{AsyncReadExt, AsyncWriteExt};
+    use futures::{stream, StreamExt};
+
+    use super::{AsyncIntegrityReader, AsyncIntegrityWriter, IntegrityStream};
+    use crate::{Algorithm, Error, Integrity, IntegrityOpts};
+
+    #[test]
+    fn read_verify() {
+        let sri = Integrity::from(b"hello world");
+        let mut reader =
+            AsyncIntegrityReader::new(&b"hello world"[..], IntegrityOpts::new()).expected(sri);
+        let mut out = Vec::new();
+        block_on(reader.read_to_end(&mut out)).unwrap();
+        assert_eq!(out, b"hello world");
+        assert_eq!(reader.algorithm(), Some(Algorithm::Sha256));
+
+        let sri = Integrity::from(b"hello world");
+        let mut reader =
+            AsyncIntegrityReader::new(&b"goodbye world"[..], IntegrityOpts::new()).expected(sri);
+        let err = block_on(reader.read_to_end(&mut Vec::new())).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn write_close() {
+        let mut writer = AsyncIntegrityWriter::new(Vec::new(), IntegrityOpts::new())
+            .expected(Integrity::from(b"hello world"));
+        block_on(async {
+            writer.write_all(b"goodbye world").await.unwrap();
+            let err = writer.close().await.unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        });
+        assert_eq!(writer.integrity(), Some(&Integrity::from(b"goodbye world")));
+    }
+
+    #[test]
+    fn stream_passthrough() {
+        let chunks = vec![Ok::<_, Error>(&b"hello "[..]), Ok(&b"world"[..])];
+        let opts = IntegrityOpts::new().algorithm(Algorithm::Sha256);
+        let mut body = IntegrityStream::new(stream::iter(chunks), opts);
+        let items: Vec<_> = block_on((&mut body).collect());
+        assert_eq!(items, vec![Ok(&b"hello "[..]), Ok(&b"world"[..])]);
+        assert_eq!(body.bytes_read(), 11);
+        assert_eq!(body.integrity(), Some(&Integrity::from(b"hello world")));
+    }
+
+    #[test]
+    fn stream_mismatch() {
+        let chunks = vec![Ok::<_, Error>(&b"goodbye "[..]), Ok(&b"world"[..])];
+        let sri = Integrity::from(b"hello world");
+        let body = IntegrityStream::new(stream::iter(chunks), IntegrityOpts::new()).expected(sri);
+        let items: Vec<_> = block_on(body.collect());
+        assert_eq!(items.len(), 3);
+        assert!(matches!(
+            items.last(),
+            Some(Err(Error::IntegrityCheckError(..)))
+        ));
+    }
+
+    #[test]
+    fn stream_expected_len() {
+        let chunks = vec![
+            Ok::<_, Error>(&b"hello "[..]),
+            Ok(&b"world"[..]),
+            Ok(&b"!"[..]),
+        ];
+        let body = IntegrityStream::new(stream::iter(chunks), IntegrityOpts::new())
+            .expected(Integrity::from(b"hello world"))
+            .expected_len(8);
+        let items: Vec<_> = block_on(body.collect());
+        assert_eq!(
+            items,
+            vec![Ok(&b"hello "[..]), Err(Error::TooManyBytes(8, 11))]
+        );
+    }
+
+    #[test]
+    fn stream_io_errors() {
+        let chunks = vec![Ok::<_, io::Error>(&b"goodbye "[..]), Ok(&b"world"[..])];
+        let sri = Integrity::from(b"hello world");
+        let body = IntegrityStream::new(stream::iter(chunks), IntegrityOpts::new()).expected(sri);
+        let items: Vec<_> = block_on(body.collect());
+        let err = items.into_iter().last().unwrap().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        let inner = err.into_inner().unwrap().downcast::<Error>().unwrap();
+        assert!(matches!(*inner, Error::IntegrityCheckError(..)));
+    }
+
+    #[test]
+    #[should_panic(expected = "input has started")]
+    fn stream_expected_after_chunk() {
+        let chunks = vec![Ok::<_, Error>(&b"hello"[..])];
+        let opts = IntegrityOpts::new().algorithm(Algorithm::Sha256);
+        let mut body = IntegrityStream::new(stream::iter(chunks), opts);
+        assert_eq!(block_on(body.next()), Some(Ok(&b"hello"[..])));
+        let _ = body.expected(Integrity::from(b"hello"));
+    }
+}
@@ -0,0 +1,300 @@
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use crate::algorithm::Algorithm;
+use crate::checker::IntegrityChecker;
+use crate::errors::Error;
+use crate::file;
+use crate::integrity::Integrity;
+
+/// Where [`BatchVerifier`](struct.BatchVerifier.html) reads an entry's data
+/// from.
+pub enum BatchSource {
+    /// A file, which is opened on the worker thread.
+    Path(PathBuf),
+    /// Any reader that can be sent to a worker thread.
+    Reader(Box<dyn Read + Send>),
+}
+
+impl std::fmt::Debug for BatchSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BatchSource::Path(path) => f.debug_tuple("Path").field(path).finish(),
+            BatchSource::Reader(_) => f.debug_tuple("Reader").finish(),
+        }
+    }
+}
+
+impl From<PathBuf> for BatchSource {
+    fn from(path: PathBuf) -> Self {
+        BatchSource::Path(path)
+    }
+}
+
+impl From<&Path> for BatchSource {
+    fn from(path: &Path) -> Self {
+        BatchSource::Path(path.to_owned())
+    }
+}
+
+impl From<&str> for BatchSource {
+    fn from(path: &str) -> Self {
+        BatchSource::Path(path.into())
+    }
+}
+
+impl From<String> for BatchSource {
+    fn from(path: String) -> Self {
+        BatchSource::Path(path.into())
+    }
+}
+
+impl From<Box<dyn Read + Send>> for BatchSource {
+    fn from(reader: Box<dyn Read + Send>) -> Self {
+        BatchSource::Reader(reader)
+    }
+}
+
+impl BatchSource {
+    fn check(self, sri: Integrity) -> Result<Algorithm, Error> {
+        let mut checker = IntegrityChecker::new(sri);
+        match self {
+            BatchSource::Path(path) => file::read_path(&path, |chunk| checker.input(chunk))?,
+            BatchSource::Reader(reader) => {
+                file::read_buffered(reader, |chunk| checker.input(chunk))?
+            }
+        };
+        checker.result()
+    }
+}
+
+/// Results from [`BatchVerifier::verify`](struct.BatchVerifier.html#method.verify).
+/// Entries are identified by their position in the input, and each list is
+/// sorted by it.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BatchReport {
+    /// Entries whose data matched, with the algorithm that matched.
+    pub passed: Vec<(usize, Algorithm)>,
+    /// Entries whose data was read but didn't pass the check.
+    pub mismatched: Vec<(usize, Error)>,
+    /// Entries that couldn't be read. These are always
+    /// [`Error::Io`](enum.Error.html#variant.Io).
+    pub io_errors: Vec<(usize, Error)>,
+    /// Whether verification stopped at the first failure because of
+    /// [`BatchVerifier::fail_fast`](struct.BatchVerifier.html#method.fail_fast).
+    /// Entries after that may not have been checked.
+    pub stopped_early: bool,
+}
+
+impl BatchReport {
+    /// Whether every entry that was checked passed.
+    pub fn is_ok(&self) -> bool {
+        self.mismatched.is_empty() && self.io_errors.is_empty()
+    }
+}
+
+/**
+Verifies many files or readers against their
+[`Integrity`](struct.Integrity.html) values on a bounded pool of worker
+threads.
+
+# Examples
+
+```no_run
+# use ssri::{BatchVerifier, Integrity};
+let manifest: Vec<(&str, Integrity)> = vec![
+    ("cache/a.tgz", "sha256-uU0nuZNNPgilLlLX2n2r+sSE7+N6U4DukIj3rOLvzek=".parse()?),
+    ("cache/b.tgz", "sha256-LPJNul+wow4m6DsqxbninhsWHlwfp0JecwQzYpOLmCQ=".parse()?),
+];
+let report = BatchVerifier::new().workers(8).verify(manifest);
+for (index, err) in report.mismatched.iter().chain(&report.io_errors) {
+    eprintln!("entry {} failed: {}", index, err);
+}
+# Ok::<(), ssri::Error>(())
+```
+*/
+#[derive(Clone, Debug)]
+pub struct BatchVerifier {
+    workers: usize,
+    fail_fast: bool,
+}
+
+impl Default for BatchVerifier {
+    fn default() -> Self {
+        BatchVerifier {
+            workers: 4,
+            fail_fast: false,
+        }
+    }
+}
+
+impl BatchVerifier {
+    /// Creates a new `BatchVerifier` with 4 workers that checks every entry.
+    pub fn new() -> BatchVerifier {
+        Self::default()
+    }
+
+    /// Sets the number of worker threads. At least one is always used.
+    pub fn workers(mut self, workers: usize) -> Self {
+        self.workers = workers.max(1);
+        self
+    }
+
+    /// Stop handing out entries as soon as one fails. Entries already being
+    /// checked still finish.
+    pub fn fail_fast(mut self, fail_fast: bool) -> Self {
+        self.fail_fast = fail_fast;
+        self
+    }
+
+    /// Checks each entry's data against its `Integrity`. `entries` is
+    /// consumed lazily, so only a few entries are held in memory at a time.
+    pub fn verify<I, S>(&self, entries: I) -> BatchReport
+    where
+        I: IntoIterator<Item = (S, Integrity)>,
+        S: Into<BatchSource>,
+    {
+        let (job_tx, job_rx) = mpsc::sync_channel::<(usize, BatchSource, Integrity)>(self.workers);
+        let job_rx = Arc::new(Mutex::new(job_rx));
+        let (result_tx, result_rx) = mpsc::channel();
+        let failed = Arc::new(AtomicBool::new(false));
+        let skipped = Arc::new(AtomicBool::new(false));
+
+        let handles = (0..self.workers)
+            .map(|_| {
+                let job_rx = Arc::clone(&job_rx);
+                let result_tx = result_tx.clone();
+                let failed = Arc::clone(&failed);
+                let skipped = Arc::clone(&skipped);
+                let fail_fast = self.fail_fast;
+                thread::spawn(move || loop {
+                    let job = job_rx.lock().unwrap().recv();
+                    let (index, source, sri) = match job {
+                        Ok(job) => job,
+                        Err(_) => break,
+                    };
+                    if fail_fast && failed.load(Ordering::Relaxed) {
+                        skipped.store(true, Ordering::Relaxed);
+                        continue;
+                    }
+                    let result = source.check(sri);
+                    if result.is_err() {
+                        failed.store(true, Ordering::Relaxed);
+                    }
+                    let _ = result_tx.send((index, result));
+                })
+            })
+            .collect::<Vec<_>>();
+        drop(result_tx);
+
+        let mut stopped_early = false;
+        for (index, (source, sri)) in entries.into_iter().enumerate() {
+            if self.fail_fast && failed.load(Ordering::Relaxed) {
+                stopped_early = true;
+                break;
+            }
+            if job_tx.send((index, source.into(), sri)).is_err() {
+                break;
+            }
+        }
+        drop(job_tx);
+
+        let mut report = BatchReport {
+            stopped_early,
+            ..BatchReport::default()
+        };
+        for (index, result) in result_rx {
+            match result {
+                Ok(algo) => report.passed.push((index, algo)),
+                Err(err @ Error::Io(..)) => report.io_errors.push((index, err)),
+                Err(err) => report.mismatched.push((index, err)),
+            }
+        }
+        for handle in handles {
+            handle.join().expect("batch verification worker panicked");
+        }
+        report.passed.sort_by_key(|(index, _)| *index);
+        report.mismatched.sort_by_key(|(index, _)| *index);
+        report.io_errors.sort_by_key(|(index, _)| *index);
+        report.stopped_early |= skipped.load(Ordering::Relaxed);
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::io::{Cursor, Read};
+
+    use super::{BatchSource, BatchVerifier};
+    use crate::{Algorithm, Error, Integrity};
+
+    fn reader(data: &'static [u8]) -> BatchSource {
+        BatchSource::from(Box::new(Cursor::new(data)) as Box<dyn Read + Send>)
+    }
+
+    #[test]
+    fn mixed_results() {
+        let path = std::env::temp_dir().join(format!("ssri-{}-batch", std::process::id()));
+        fs::write(&path, b"on disk").unwrap();
+        let entries = vec![
+            (reader(b"hello"), Integrity::from(b"hello")),
+            (reader(b"goodbye"), Integrity::from(b"hello")),
+            (
+                BatchSource::from(path.as_path()),
+                Integrity::from(b"on disk"),
+            ),
+            (
+                BatchSource::from("/ssri/does/not/exist"),
+                Integrity::from(b"hello"),
+            ),
+        ];
+        let report = BatchVerifier::new().workers(2).verify(entries);
+        fs::remove_file(path).unwrap();
+        assert_eq!(
+            report.passed,
+            vec![(0, Algorithm::Sha256), (2, Algorithm::Sha256)]
+        );
+        assert_eq!(report.mismatched.len(), 1);
+        assert_eq!(report.mismatched[0].0, 1);
+        assert!(matches!(
+            report.mismatched[0].1,
+            Error::IntegrityCheckError(..)
+        ));
+        assert_eq!(report.io_errors.len(), 1);
+        assert_eq!(report.io_errors[0].0, 3);
+        assert!(!report.stopped_early);
+        assert!(!report.is_ok());
+    }
+
+    #[test]
+    fn many_entries() {
+        let entries = (0..500).map(|i| {
+            let data = if i == 250 { &b"bad"[..] } else { &b"good"[..] };
+            (reader(data), Integrity::from(b"good"))
+        });
+        let report = BatchVerifier::new().verify(entries);
+        assert_eq!(report.passed.len(), 499);
+        assert_eq!(report.mismatched.len(), 1);
+        assert_eq!(report.mismatched[0].0, 250);
+    }
+
+    #[test]
+    fn fail_fast() {
+        let entries = (0..10_000).map(|i| {
+            let data = if i == 0 { &b"bad"[..] } else { &b"good"[..] };
+            (reader(data), Integrity::from(b"good"))
+        });
+        let report = BatchVerifier::new()
+            .workers(1)
+            .fail_fast(true)
+            .verify(entries);
+        assert_eq!(report.mismatched.len(), 1);
+        assert!(report.stopped_early);
+        assert!(report.passed.len() < 9_999);
+    }
+}
@@ -1,8 +1,61 @@
 use crate::algorithm::Algorithm;
 use crate::integrity::Integrity;
 use crate::opts::IntegrityOpts;
+use crate::policy::VerificationPolicy;
 use crate::Error;
 
+/**
+Which of the algorithms in an [`Integrity`](struct.Integrity.html) an
+[`IntegrityChecker`](struct.IntegrityChecker.html) checks data with, and how
+many of them need to match.
+
+Algorithms `ssri` doesn't know about are never checked.
+*/
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MatchPolicy {
+    /// Only check the strongest algorithm, as picked by
+    /// [`Integrity::pick_algorithm`](struct.Integrity.html#method.pick_algorithm).
+    /// This is the default.
+    Strongest,
+    /// Check every algorithm, and require all of them to match. This catches
+    /// tampering where only a weaker hash was changed.
+    All,
+    /// Check every algorithm, and require at least one of them to match.
+    Any,
+}
+
+impl Default for MatchPolicy {
+    fn default() -> Self {
+        MatchPolicy::Strongest
+    }
+}
+
+/// Per-algorithm results from
+/// [`IntegrityChecker::report`](struct.IntegrityChecker.html#method.report).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CheckReport {
+    /// Each algorithm the data was checked with, strongest first, and whether
+    /// it matched.
+    pub outcomes: Vec<(Algorithm, bool)>,
+    /// The `Integrity` generated from the data.
+    pub actual: Integrity,
+    /// Whether the data passed, according to the `MatchPolicy` used.
+    pub passed: bool,
+}
+
+impl CheckReport {
+    /// The strongest algorithm that matched, if the check passed.
+    pub fn algorithm(&self) -> Option<Algorithm> {
+        if !self.passed {
+            return None;
+        }
+        self.outcomes
+            .iter()
+            .find(|(_, matched)| *matched)
+            .map(|(algo, _)| *algo)
+    }
+}
+
 /**
 Check data against an [`Integrity`](struct.Integrity.html).
 
@@ -15,41 +68,226 @@ let sri = Integrity::from(&data);
 let checker = IntegrityChecker::new(sri).chain(&data);
 assert_eq!(checker.result().unwrap(), Algorithm::Sha256);
 ```
+
+Requiring every algorithm to match:
+
+```
+# use ssri::{Algorithm, Integrity, IntegrityChecker, IntegrityOpts, MatchPolicy};
+let data = b"hello world";
+let sha512 = IntegrityOpts::new().algorithm(Algorithm::Sha512).chain(&data).result();
+// The sha256 hash is of different data, so it has been tampered with.
+let sri = sha512.concat(Integrity::from(b"malicious").concat("sha1-Kq5sNclPz7QV2+lfQIuc6R7oRu0=".parse().unwrap()));
+let report = IntegrityChecker::new(sri.clone())
+    .policy(MatchPolicy::All)
+    .chain(&data)
+    .report();
+assert!(!report.passed);
+assert_eq!(
+    report.outcomes,
+    vec![(Algorithm::Sha512, true), (Algorithm::Sha256, false), (Algorithm::Sha1, true)]
+);
+// The default policy only looks at sha512.
+assert!(IntegrityChecker::new(sri).chain(&data).result().is_ok());
+```
 */
 #[derive(Debug)]
 pub struct IntegrityChecker {
     sri: Integrity,
+    allowed: Integrity,
     builder: IntegrityOpts,
+    policy: MatchPolicy,
+    expected_len: Option<u64>,
+    bytes: u64,
 }
 
 impl IntegrityChecker {
     /// Creates a new `IntegrityChecker` builder. Use this to verify chunked
     /// data.
     pub fn new(sri: Integrity) -> IntegrityChecker {
-        let builder = IntegrityOpts::new().algorithm(sri.pick_algorithm());
-        IntegrityChecker { sri, builder }
+        let policy = MatchPolicy::default();
+        let allowed = VerificationPolicy::default().filter(&sri);
+        let builder = builder_for(&allowed, policy);
+        IntegrityChecker {
+            sri,
+            allowed,
+            builder,
+            policy,
+            expected_len: None,
+            bytes: 0,
+        }
+    }
+    /// Sets which algorithms data is checked with, and how many of them need
+    /// to match. Defaults to [`MatchPolicy::Strongest`].
+    ///
+    /// Panics if called after data has been inputted.
+    pub fn policy(mut self, policy: MatchPolicy) -> Self {
+        if self.builder.is_disturbed() {
+            panic!("Can't change the policy if IntegrityChecker::input() has already been called");
+        }
+        self.builder = builder_for(&self.allowed, policy);
+        self.policy = policy;
+        self
+    }
+    /// Sets which algorithms the checker trusts. Hashes using any other
+    /// algorithm are ignored, and if none are left, [`result()`](#method.result)
+    /// fails with [`Error::AlgorithmNotAllowed`].
+    ///
+    /// Panics if called after data has been inputted.
+    pub fn verification_policy(mut self, policy: &VerificationPolicy) -> Self {
+        if self.builder.is_disturbed() {
+            panic!("Can't change the verification policy if IntegrityChecker::input() has already been called");
+        }
+        self.allowed = policy.filter(&self.sri);
+        self.builder = builder_for(&self.allowed, self.policy);
+        self
+    }
+    /// Sets the number of bytes the data is expected to have. Going over it
+    /// makes [`try_input()`](#method.try_input) fail right away, and stops
+    /// any further data from being hashed. Either way,
+    /// [`result()`](#method.result) fails with [`Error::TooManyBytes`] or
+    /// [`Error::TooFewBytes`] if the length doesn't match.
+    pub fn expected_len(mut self, len: u64) -> Self {
+        self.expected_len = Some(len);
+        self
     }
     /// Add some data to the running checker.
     pub fn input<B: AsRef<[u8]>>(&mut self, data: B) {
+        let _ = self.try_input(data);
+    }
+    /// Same as `IntegrityChecker::input`, but fails with
+    /// [`Error::TooManyBytes`] as soon as the data goes over the expected
+    /// length, if one was set.
+    ///
+    /// # Example
+    /// ```
+    /// # use ssri::{Error, Integrity, IntegrityChecker};
+    /// let sri = Integrity::from(b"hello");
+    /// let mut checker = IntegrityChecker::new(sri).expected_len(5);
+    /// checker.try_input(b"hel").unwrap();
+    /// assert_eq!(checker.try_input(b"lo!"), Err(Error::TooManyBytes(5, 6)));
+    /// ```
+    pub fn try_input<B: AsRef<[u8]>>(&mut self, data: B) -> Result<(), Error> {
+        let data = data.as_ref();
+        self.bytes += data.len() as u64;
+        if let Some(expected) = self.expected_len {
+            if self.bytes > expected {
+                return Err(Error::TooManyBytes(expected, self.bytes));
+            }
+        }
         self.builder.input(data);
+        Ok(())
     }
     /// Same as `IntegrityChecker::input`, but allows chained calls.
     pub fn chain<B: AsRef<[u8]>>(mut self, data: B) -> Self {
-        self.builder.input(data);
+        self.input(data);
         self
     }
-    /// Returns the matching algorithm if the inputted data matches the input `Integrity`.
+    /// Returns the matching algorithm if the inputted data matches the input
+    /// `Integrity`. With a policy that checks several algorithms, this is the
+    /// strongest one that matched.
     pub fn result(self) -> Result<Algorithm, Error> {
-        let sri = self.builder.result();
-        let algo = self.sri.pick_algorithm();
-        let wanted = self.sri;
-        wanted
-            .hashes
-            .iter()
-            .take_while(|h| h.algorithm == algo)
-            .find(|&h| *h == sri.hashes[0])
-            .map(|_| algo)
-            .ok_or(Error::IntegrityCheckError(wanted, sri))
+        self.check_len()?;
+        if self.allowed.hashes.is_empty()
+            && self
+                .sri
+                .hashes
+                .iter()
+                .any(|h| !matches!(h.algorithm, Algorithm::Unknown(_)))
+        {
+            return Err(Error::AlgorithmNotAllowed(self.sri));
+        }
+        let wanted = self.sri.clone();
+        let report = self.report();
+        report
+            .algorithm()
+            .ok_or(Error::IntegrityCheckError(wanted, report.actual))
+    }
+    /// Checks the inputted data, returning the outcome for every algorithm
+    /// that was checked. Algorithms the verification policy doesn't allow
+    /// aren't checked. The report never passes if the data's length didn't
+    /// match the expected one.
+    pub fn report(self) -> CheckReport {
+        let len_ok = self.check_len().is_ok();
+        let mut report = report(&self.allowed, self.builder.result(), self.policy);
+        report.passed &= len_ok;
+        report
+    }
+    fn check_len(&self) -> Result<(), Error> {
+        match self.expected_len {
+            Some(expected) if self.bytes > expected => {
+                Err(Error::TooManyBytes(expected, self.bytes))
+            }
+            Some(expected) if self.bytes < expected => {
+                Err(Error::TooFewBytes(expected, self.bytes))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+fn builder_for(sri: &Integrity, policy: MatchPolicy) -> IntegrityOpts {
+    let mut builder = IntegrityOpts::new();
+    match policy {
+        MatchPolicy::Strongest => {
+            // With only unknown algorithms there's nothing to hash with, so
+            // the check will always fail.
+            if let Some(algo) = sri.hashes.first().map(|h| h.algorithm) {
+                if !matches!(algo, Algorithm::Unknown(_)) {
+                    builder = builder.algorithm(algo);
+                }
+            }
+        }
+        MatchPolicy::All | MatchPolicy::Any => {
+            for hash in &sri.hashes {
+                let algo = hash.algorithm;
+                if !matches!(algo, Algorithm::Unknown(_)) && !builder.has_algorithm(algo) {
+                    builder = builder.algorithm(algo);
+                }
+            }
+        }
+    }
+    builder
+}
+
+/// Checks `actual` against `wanted`. `actual` should have one hash for each
+/// algorithm to check.
+pub(crate) fn report(wanted: &Integrity, actual: Integrity, policy: MatchPolicy) -> CheckReport {
+    let strongest = wanted.hashes.first().map(|h| h.algorithm);
+    let outcomes = actual
+        .hashes
+        .iter()
+        .filter(|a| policy != MatchPolicy::Strongest || Some(a.algorithm) == strongest)
+        .map(|a| {
+            let matched = wanted
+                .hashes
+                .iter()
+                .filter(|h| h.algorithm == a.algorithm)
+                .any(|h| h.ct_eq(a));
+            (a.algorithm, matched)
+        })
+        .collect::<Vec<_>>();
+    let passed = match policy {
+        MatchPolicy::Strongest => outcomes.first().map_or(false, |(_, m)| *m),
+        MatchPolicy::All => !outcomes.is_empty() && outcomes.iter().all(|(_, m)| *m),
+        MatchPolicy::Any => outcomes.iter().any(|(_, m)| *m),
+    };
+    CheckReport {
+        outcomes,
+        actual,
+        passed,
+    }
+}
+
+/// Checks `actual` against `wanted`, using the algorithm `wanted` prefers.
+/// `actual` may contain hashes for other algorithms, which are ignored.
+pub(crate) fn verify(
+    wanted: Integrity,
+    actual: Integrity,
+) -> Result<(Algorithm, Integrity), Error> {
+    let report = report(&wanted, actual, MatchPolicy::Strongest);
+    match report.algorithm() {
+        Some(algo) => Ok((algo, report.actual)),
+        None => Err(Error::IntegrityCheckError(wanted, report.actual)),
     }
 }
 
@@ -58,6 +296,9 @@ mod tests {
     use super::Algorithm;
     use super::Integrity;
     use super::IntegrityChecker;
+    use super::MatchPolicy;
+    use crate::UnknownTokens;
+    use crate::{Error, IntegrityOpts, VerificationPolicy};
 
     #[test]
     fn basic_test() {
@@ -75,4 +316,140 @@ mod tests {
         let result = IntegrityChecker::new(sri).chain(b"hello world").result();
         assert_eq!(result.unwrap(), Algorithm::Sha256)
     }
+    #[test]
+    fn all_algorithms() {
+        let data = b"hello world";
+        let good = IntegrityOpts::new()
+            .algorithm(Algorithm::Sha512)
+            .algorithm(Algorithm::Sha1)
+            .chain(data)
+            .result();
+        let report = IntegrityChecker::new(good.clone())
+            .policy(MatchPolicy::All)
+            .chain(data)
+            .report();
+        assert!(report.passed);
+        assert_eq!(
+            report.outcomes,
+            vec![(Algorithm::Sha512, true), (Algorithm::Sha1, true)]
+        );
+        assert_eq!(report.actual, good);
+
+        // Only the weaker hash was swapped out.
+        let tampered = IntegrityOpts::new()
+            .algorithm(Algorithm::Sha512)
+            .chain(data)
+            .result()
+            .concat(
+                IntegrityOpts::new()
+                    .algorithm(Algorithm::Sha1)
+                    .chain(b"evil")
+                    .result(),
+            );
+        let result = IntegrityChecker::new(tampered.clone())
+            .policy(MatchPolicy::All)
+            .chain(data)
+            .result();
+        assert!(result.is_err());
+        let result = IntegrityChecker::new(tampered.clone())
+            .policy(MatchPolicy::Any)
+            .chain(data)
+            .result();
+        assert_eq!(result, Ok(Algorithm::Sha512));
+        let result = IntegrityChecker::new(tampered).chain(data).result();
+        assert_eq!(result, Ok(Algorithm::Sha512));
+    }
+
+    #[test]
+    fn any_picks_strongest_match() {
+        let data = b"hello world";
+        let sri = IntegrityOpts::new()
+            .algorithm(Algorithm::Sha512)
+            .chain(b"evil")
+            .result()
+            .concat(Integrity::from(data));
+        let report = IntegrityChecker::new(sri)
+            .policy(MatchPolicy::Any)
+            .chain(data)
+            .report();
+        assert_eq!(report.algorithm(), Some(Algorithm::Sha256));
+        assert_eq!(
+            report.outcomes,
+            vec![(Algorithm::Sha512, false), (Algorithm::Sha256, true)]
+        );
+    }
+
+    #[test]
+    fn verification_policy() {
+        let data = b"hello world";
+        let sri = IntegrityOpts::new()
+            .algorithm(Algorithm::Sha1)
+            .algorithm(Algorithm::Xxh3)
+            .chain(data)
+            .result();
+        let policy = VerificationPolicy::new().minimum(Algorithm::Sha256);
+        let result = IntegrityChecker::new(sri.clone())
+            .verification_policy(&policy)
+            .chain(data)
+            .result();
+        assert_eq!(result, Err(Error::AlgorithmNotAllowed(sri.clone())));
+
+        let policy = VerificationPolicy::new().non_cryptographic(false);
+        let result = IntegrityChecker::new(sri.clone())
+            .verification_policy(&policy)
+            .chain(data)
+            .result();
+        assert_eq!(result, Ok(Algorithm::Sha1));
+
+        // The strongest allowed algorithm is used, even when a stronger
+        // one is present.
+        let sri = sri.concat(Integrity::from(b"something else"));
+        let policy = VerificationPolicy::new().deny(Algorithm::Sha256);
+        let result = IntegrityChecker::new(sri)
+            .verification_policy(&policy)
+            .chain(data)
+            .result();
+        assert_eq!(result, Ok(Algorithm::Sha1));
+    }
+
+    #[test]
+    fn expected_len() {
+        let data = b"hello world";
+        let sri = Integrity::from(data);
+        let result = IntegrityChecker::new(sri.clone())
+            .expected_len(11)
+            .chain(data)
+            .result();
+        assert_eq!(result, Ok(Algorithm::Sha256));
+
+        let mut checker = IntegrityChecker::new(sri.clone()).expected_len(8);
+        assert_eq!(checker.try_input(b"hello"), Ok(()));
+        assert_eq!(
+            checker.try_input(b" world"),
+            Err(Error::TooManyBytes(8, 11))
+        );
+        assert_eq!(checker.try_input(b"!"), Err(Error::TooManyBytes(8, 12)));
+        assert_eq!(checker.result(), Err(Error::TooManyBytes(8, 12)));
+
+        let result = IntegrityChecker::new(sri.clone())
+            .expected_len(20)
+            .chain(data)
+            .result();
+        assert_eq!(result, Err(Error::TooFewBytes(20, 11)));
+        let report = IntegrityChecker::new(sri)
+            .expected_len(20)
+            .chain(data)
+            .report();
+        assert!(!report.passed);
+        assert_eq!(report.outcomes, vec![(Algorithm::Sha256, true)]);
+    }
+
+    #[test]
+    fn unknown_only() {
+        let report = Integrity::parse_lenient("md5-deadbeef", UnknownTokens::Keep);
+        let result = IntegrityChecker::new(report.integrity)
+            .chain(b"hello world")
+            .result();
+        assert!(result.is_err())
+    }
 }
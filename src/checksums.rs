@@ -0,0 +1,364 @@
+use std::fmt;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use crate::algorithm::Algorithm;
+use crate::batch::BatchVerifier;
+use crate::errors::Error;
+use crate::integrity::Integrity;
+
+/// Line format used by [`ChecksumList::write_to`](struct.ChecksumList.html#method.write_to).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChecksumFormat {
+    /// `<hex>  <path>`, as written by `sha256sum` and friends.
+    Gnu,
+    /// `SHA256 (<path>) = <hex>`, as written by BSD `sha256` and
+    /// `sha256sum --tag`.
+    Bsd,
+}
+
+/**
+The entries of a checksum listing like a `SHA256SUMS` file.
+
+Both GNU (`<hex>  <path>`, with an optional `*` binary marker) and BSD
+(`SHA256 (<path>) = <hex>`) lines are understood, including the backslash
+escapes coreutils uses for paths with line breaks or backslashes in them.
+Blank lines and `#` comments are skipped.
+
+# Examples
+
+```no_run
+# use ssri::{Algorithm, ChecksumList};
+let sums = std::fs::read_to_string("dist/SHA256SUMS").unwrap();
+let list = ChecksumList::parse(&sums, Algorithm::Sha256)?;
+let report = list.verify_all("dist");
+print!("{}", report);
+assert!(report.is_ok());
+# Ok::<(), ssri::Error>(())
+```
+*/
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ChecksumList {
+    /// Listed paths, in order, with their expected `Integrity`.
+    pub entries: Vec<(PathBuf, Integrity)>,
+}
+
+impl ChecksumList {
+    /// Parses a checksum listing. GNU lines don't name their algorithm, so
+    /// `algorithm` is used for them. BSD lines always use the algorithm they
+    /// are tagged with.
+    ///
+    /// # Example
+    /// ```
+    /// use ssri::{Algorithm, ChecksumList, Integrity};
+    ///
+    /// let list = ChecksumList::parse(
+    ///     "b94d27b9934d3e08a52e52d7da7dabfac484efe37a5380ee9088f7ace2efcde9 *hello.txt\n",
+    ///     Algorithm::Sha256,
+    /// )?;
+    /// assert_eq!(list.entries[0].0.to_str(), Some("hello.txt"));
+    /// assert_eq!(list.entries[0].1, Integrity::from(b"hello world"));
+    /// # Ok::<(), ssri::Error>(())
+    /// ```
+    pub fn parse(s: &str, algorithm: Algorithm) -> Result<ChecksumList, Error> {
+        let mut entries = Vec::new();
+        for line in s.lines() {
+            let line = line.strip_suffix('\r').unwrap_or(line);
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let invalid =
+                || Error::ParseIntegrityError(format!("invalid checksum line `{}`", line));
+            let (escaped, rest) = match line.strip_prefix('\\') {
+                Some(rest) => (true, rest),
+                None => (false, line),
+            };
+            let (algorithm, hex, path) = match parse_bsd(rest) {
+                Some((tag, path, hex)) => (tag.to_ascii_lowercase().parse()?, hex, path),
+                None => {
+                    let (hex, path) = rest.split_once(' ').ok_or_else(invalid)?;
+                    let path = path
+                        .strip_prefix(' ')
+                        .or_else(|| path.strip_prefix('*'))
+                        .ok_or_else(invalid)?;
+                    (algorithm, hex, path)
+                }
+            };
+            let path = if escaped {
+                unescape(path).ok_or_else(invalid)?
+            } else {
+                path.to_owned()
+            };
+            if path.is_empty() {
+                return Err(invalid());
+            }
+            let integrity = Integrity::from_hex(hex, algorithm)?;
+            entries.push((PathBuf::from(path), integrity));
+        }
+        Ok(ChecksumList { entries })
+    }
+
+    /// Writes one line per entry in `format`, using the hex digest of each
+    /// entry's strongest algorithm.
+    ///
+    /// # Example
+    /// ```
+    /// use ssri::{ChecksumFormat, ChecksumList, Integrity};
+    ///
+    /// let list = ChecksumList {
+    ///     entries: vec![("hello.txt".into(), Integrity::from(b"hello world"))],
+    /// };
+    /// let mut out = Vec::new();
+    /// list.write_to(&mut out, ChecksumFormat::Bsd)?;
+    /// assert_eq!(
+    ///     String::from_utf8(out).unwrap(),
+    ///     "SHA256 (hello.txt) = b94d27b9934d3e08a52e52d7da7dabfac484efe37a5380ee9088f7ace2efcde9\n"
+    /// );
+    /// # Ok::<(), ssri::Error>(())
+    /// ```
+    pub fn write_to<W: Write>(&self, mut out: W, format: ChecksumFormat) -> Result<(), Error> {
+        for (path, integrity) in &self.entries {
+            let path = path.to_str().ok_or_else(|| {
+                Error::from(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("path is not valid UTF-8: {}", path.display()),
+                ))
+            })?;
+            let hash = integrity
+                .hashes
+                .first()
+                .ok_or_else(|| Error::ParseIntegrityError(format!("no hashes for {}", path)))?;
+            hash.validate()?;
+            let hex = hex::encode(hash.as_bytes().unwrap_or_default());
+            let (prefix, path) = escape(path);
+            match format {
+                ChecksumFormat::Gnu => writeln!(out, "{}{}  {}", prefix, hex, path)?,
+                ChecksumFormat::Bsd => writeln!(
+                    out,
+                    "{}{} ({}) = {}",
+                    prefix,
+                    hash.algorithm.to_string().to_ascii_uppercase(),
+                    path,
+                    hex
+                )?,
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks every listed file, like `sha256sum -c`. Relative paths are
+    /// resolved against `base`. Files are read on a
+    /// [`BatchVerifier`](struct.BatchVerifier.html) pool.
+    pub fn verify_all<P: AsRef<Path>>(&self, base: P) -> ChecksumReport {
+        let base = base.as_ref();
+        let batch = BatchVerifier::new().verify(
+            self.entries
+                .iter()
+                .map(|(path, sri)| (base.join(path), sri.clone())),
+        );
+        let mut results = self
+            .entries
+            .iter()
+            .map(|(path, _)| (path.clone(), None))
+            .collect::<Vec<_>>();
+        for (index, algorithm) in batch.passed {
+            results[index].1 = Some(Ok(algorithm));
+        }
+        for (index, err) in batch.mismatched.into_iter().chain(batch.io_errors) {
+            results[index].1 = Some(Err(err));
+        }
+        ChecksumReport {
+            results: results
+                .into_iter()
+                .filter_map(|(path, result)| result.map(|result| (path, result)))
+                .collect(),
+        }
+    }
+}
+
+/// Splits a BSD `TAG (path) = hex` line.
+fn parse_bsd(line: &str) -> Option<(&str, &str, &str)> {
+    let (tag, rest) = line.split_once(" (")?;
+    let (path, hex) = rest.rsplit_once(") = ")?;
+    if tag.is_empty() || tag.contains(' ') {
+        return None;
+    }
+    Some((tag, path, hex))
+}
+
+/// Escapes `path` the way coreutils does, returning the `\` line prefix
+/// that marks an escaped line, if one is needed.
+fn escape(path: &str) -> (&'static str, String) {
+    if !path.contains(|c| matches!(c, '\\' | '\n' | '\r')) {
+        return ("", path.to_owned());
+    }
+    let escaped = path
+        .replace('\\', "\\\\")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r");
+    ("\\", escaped)
+}
+
+fn unescape(path: &str) -> Option<String> {
+    let mut out = String::with_capacity(path.len());
+    let mut chars = path.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        out.push(match chars.next()? {
+            '\\' => '\\',
+            'n' => '\n',
+            'r' => '\r',
+            _ => return None,
+        });
+    }
+    Some(out)
+}
+
+/// Per-file results from
+/// [`ChecksumList::verify_all`](struct.ChecksumList.html#method.verify_all),
+/// in listing order.
+///
+/// Displays like the output of `sha256sum -c`: one `<path>: OK`,
+/// `<path>: FAILED` or `<path>: FAILED open or read` line per file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ChecksumReport {
+    /// Each listed path, with the matching algorithm or the reason it failed.
+    pub results: Vec<(PathBuf, Result<Algorithm, Error>)>,
+}
+
+impl ChecksumReport {
+    /// Whether every listed file matched.
+    pub fn is_ok(&self) -> bool {
+        self.results.iter().all(|(_, result)| result.is_ok())
+    }
+}
+
+impl fmt::Display for ChecksumReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (path, result) in &self.results {
+            let status = match result {
+                Ok(_) => "OK",
+                Err(Error::Io(..)) => "FAILED open or read",
+                Err(_) => "FAILED",
+            };
+            writeln!(f, "{}: {}", path.display(), status)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::Path;
+
+    use super::{ChecksumFormat, ChecksumList};
+    use crate::{Algorithm, Error, Integrity, IntegrityOpts};
+
+    const HELLO: &str = "b94d27b9934d3e08a52e52d7da7dabfac484efe37a5380ee9088f7ace2efcde9";
+
+    #[test]
+    fn parse() {
+        let sha512 = IntegrityOpts::new()
+            .algorithm(Algorithm::Sha512)
+            .chain(b"hello world")
+            .result();
+        let listing = format!(
+            "# comment\n{hello}  text.txt\n{hello} *bin.txt\r\n\n\\{hello}  a\\\\b\\nc\nSHA512 (with (parens)) = {sha512}\n",
+            hello = HELLO,
+            sha512 = sha512.to_hex().1
+        );
+        let list = ChecksumList::parse(&listing, Algorithm::Sha256).unwrap();
+        let hello = Integrity::from(b"hello world");
+        assert_eq!(
+            list.entries,
+            vec![
+                ("text.txt".into(), hello.clone()),
+                ("bin.txt".into(), hello.clone()),
+                ("a\\b\nc".into(), hello),
+                ("with (parens)".into(), sha512),
+            ]
+        );
+
+        for bad in &[
+            "nothex  file",
+            "abcd  file",
+            &format!("{} file", HELLO),
+            &format!("{}  ", HELLO),
+            &format!("\\{}  bad\\escape", HELLO),
+            &format!("MD5 (file) = {}", HELLO),
+        ] {
+            assert!(
+                ChecksumList::parse(bad, Algorithm::Sha256).is_err(),
+                "{}",
+                bad
+            );
+        }
+    }
+
+    #[test]
+    fn write_roundtrip() {
+        let list = ChecksumList {
+            entries: vec![
+                ("dist/app.tgz".into(), Integrity::from(b"app")),
+                ("odd\\name\n".into(), Integrity::from(b"odd")),
+                (
+                    "fast.bin".into(),
+                    IntegrityOpts::new()
+                        .algorithm(Algorithm::Xxh3)
+                        .chain(b"fast")
+                        .result(),
+                ),
+            ],
+        };
+        for format in &[ChecksumFormat::Gnu, ChecksumFormat::Bsd] {
+            let mut out = Vec::new();
+            list.write_to(&mut out, *format).unwrap();
+            let out = String::from_utf8(out).unwrap();
+            assert_eq!(out.lines().count(), 3);
+            assert!(out.lines().nth(1).unwrap().starts_with('\\'));
+            if *format == ChecksumFormat::Bsd {
+                assert!(out.contains("XXH3 (fast.bin) = "));
+                assert_eq!(ChecksumList::parse(&out, Algorithm::Sha256).unwrap(), list);
+            } else {
+                // GNU lines don't say which algorithm they use.
+                let sha256 = out.lines().take(2).collect::<Vec<_>>().join("\n");
+                let parsed = ChecksumList::parse(&sha256, Algorithm::Sha256).unwrap();
+                assert_eq!(parsed.entries, list.entries[..2]);
+                assert!(ChecksumList::parse(&out, Algorithm::Sha256).is_err());
+            }
+        }
+    }
+
+    #[test]
+    fn verify_all() {
+        let dir = std::env::temp_dir().join(format!("ssri-{}-checksums", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("good.txt"), b"hello world").unwrap();
+        fs::write(dir.join("bad.txt"), b"goodbye world").unwrap();
+        let listing = format!(
+            "{hello}  good.txt\n{hello}  bad.txt\n{hello}  missing.txt\n",
+            hello = HELLO
+        );
+        let report = ChecksumList::parse(&listing, Algorithm::Sha256)
+            .unwrap()
+            .verify_all(&dir);
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert!(!report.is_ok());
+        assert_eq!(report.results[0].0, Path::new("good.txt"));
+        assert_eq!(report.results[0].1, Ok(Algorithm::Sha256));
+        assert!(matches!(
+            report.results[1].1,
+            Err(Error::IntegrityCheckError(..))
+        ));
+        assert!(matches!(report.results[2].1, Err(Error::Io(..))));
+        assert_eq!(
+            report.to_string(),
+            "good.txt: OK\nbad.txt: FAILED\nmissing.txt: FAILED open or read\n"
+        );
+    }
+}
@@ -0,0 +1,539 @@
+use std::borrow::Cow;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::algorithm::Algorithm;
+use crate::errors::Error;
+use crate::integrity::Integrity;
+use crate::opts::IntegrityOpts;
+
+/// Mode recorded for regular files.
+const MODE_FILE: u32 = 0o100644;
+/// Mode recorded for files with any executable bit set.
+const MODE_EXECUTABLE: u32 = 0o100755;
+/// Mode recorded for symlinks, whose target is hashed instead of contents.
+const MODE_SYMLINK: u32 = 0o120000;
+
+/// Prefixed to the data the root `Integrity` is generated from, so it can't
+/// collide with the hash of a plain file.
+const ROOT_TAG: &[u8] = b"ssri-dirhash-v1\0";
+
+/// First line of a manifest's string form, followed by the root algorithm.
+const MANIFEST_HEADER: &str = "# ssri-dirhash-v1";
+
+/// One file in a [`DirManifest`](struct.DirManifest.html).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DirEntry {
+    /// Path relative to the directory root, with `/` separators.
+    pub path: String,
+    /// Git-style file mode: `100644` for regular files, `100755` for
+    /// executables and `120000` for symlinks.
+    pub mode: u32,
+    /// Integrity of the file's contents, or of the link target for symlinks.
+    pub integrity: Integrity,
+}
+
+/**
+Every file in a directory tree, sorted by path, and the root
+[`Integrity`](struct.Integrity.html) that covers all of them.
+
+Manifests round-trip through their string form, a
+`# ssri-dirhash-v1 <algorithm>` header naming the algorithm of the root
+followed by one `<mode> <integrity> <path>` line per file, so they can be
+stored and checked against later with [`DirIntegrity::verify`](struct.DirIntegrity.html#method.verify).
+*/
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DirManifest {
+    /// The files in the tree, sorted by path.
+    pub entries: Vec<DirEntry>,
+    /// Integrity covering every entry's path, mode and digest.
+    pub root: Integrity,
+}
+
+impl DirManifest {
+    fn new(mut entries: Vec<DirEntry>, algorithm: Algorithm) -> DirManifest {
+        entries.sort_by(|a, b| a.path.cmp(&b.path));
+        let mut opts = IntegrityOpts::new().algorithm(algorithm).chain(ROOT_TAG);
+        for entry in &entries {
+            opts.input(entry.path.as_bytes());
+            opts.input([0]);
+            opts.input(entry.mode.to_be_bytes());
+            for hash in &entry.integrity.hashes {
+                opts.input(hash.to_string());
+                opts.input([0]);
+            }
+            opts.input([0]);
+        }
+        DirManifest {
+            entries,
+            root: opts.result(),
+        }
+    }
+
+    /// Compares this manifest with `other`, listing the paths that are only
+    /// in `other`, only in this one, or in both with a different mode or
+    /// digest.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use ssri::DirIntegrity;
+    /// let before = DirIntegrity::new().manifest("node_modules/lodash")?;
+    /// // ...
+    /// let after = DirIntegrity::new().manifest("node_modules/lodash")?;
+    /// for path in before.diff(&after).changed {
+    ///     println!("{} was modified", path);
+    /// }
+    /// # Ok::<(), ssri::Error>(())
+    /// ```
+    pub fn diff(&self, other: &DirManifest) -> DirDiff {
+        let mut diff = DirDiff::default();
+        let mut ours = self.entries.iter().peekable();
+        let mut theirs = other.entries.iter().peekable();
+        loop {
+            match (ours.peek(), theirs.peek()) {
+                (Some(a), Some(b)) if a.path == b.path => {
+                    if a.mode != b.mode || !integrity_eq(&a.integrity, &b.integrity) {
+                        diff.changed.push(a.path.clone());
+                    }
+                    ours.next();
+                    theirs.next();
+                }
+                (Some(a), Some(b)) if a.path < b.path => {
+                    diff.removed.push(a.path.clone());
+                    ours.next();
+                }
+                (Some(_), Some(b)) => {
+                    diff.added.push(b.path.clone());
+                    theirs.next();
+                }
+                (Some(a), None) => {
+                    diff.removed.push(a.path.clone());
+                    ours.next();
+                }
+                (None, Some(b)) => {
+                    diff.added.push(b.path.clone());
+                    theirs.next();
+                }
+                (None, None) => return diff,
+            }
+        }
+    }
+}
+
+impl fmt::Display for DirManifest {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{} {}", MANIFEST_HEADER, self.root.pick_algorithm())?;
+        for entry in &self.entries {
+            writeln!(f, "{:06o} {} {}", entry.mode, entry.integrity, entry.path)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::str::FromStr for DirManifest {
+    type Err = Error;
+
+    /// Parses a manifest from its string form. The root `Integrity` is
+    /// recomputed from the entries, using the algorithm named in the header.
+    fn from_str(s: &str) -> Result<DirManifest, Self::Err> {
+        let invalid = |line: &str| {
+            Error::ParseIntegrityError(format!("invalid directory manifest line `{}`", line))
+        };
+        let mut lines = s.lines().filter(|l| !l.is_empty());
+        let algorithm = lines
+            .next()
+            .and_then(|line| line.strip_prefix(MANIFEST_HEADER)?.strip_prefix(' '))
+            .ok_or_else(|| {
+                Error::ParseIntegrityError(format!(
+                    "directory manifest doesn't start with `{} <algorithm>`",
+                    MANIFEST_HEADER
+                ))
+            })?
+            .parse::<Algorithm>()?;
+        let mut entries = Vec::new();
+        for line in lines {
+            let mut parts = line.splitn(3, ' ');
+            let (mode, integrity, path) = match (parts.next(), parts.next(), parts.next()) {
+                (Some(mode), Some(integrity), Some(path)) => (mode, integrity, path),
+                _ => return Err(invalid(line)),
+            };
+            entries.push(DirEntry {
+                path: path.to_owned(),
+                mode: u32::from_str_radix(mode, 8).map_err(|_| invalid(line))?,
+                integrity: Integrity::parse_strict(integrity)?,
+            });
+        }
+        Ok(DirManifest::new(entries, algorithm))
+    }
+}
+
+/// Paths that differ between two [`DirManifest`](struct.DirManifest.html)s.
+/// Each list is sorted.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DirDiff {
+    /// Paths that weren't expected.
+    pub added: Vec<String>,
+    /// Expected paths that are missing.
+    pub removed: Vec<String>,
+    /// Paths whose mode or contents changed.
+    pub changed: Vec<String>,
+}
+
+impl DirDiff {
+    /// Whether there are no differences.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+impl fmt::Display for DirDiff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let lists = [
+            ("+", &self.added),
+            ("-", &self.removed),
+            ("~", &self.changed),
+        ];
+        let mut first = true;
+        for (marker, paths) in lists.iter() {
+            for path in paths.iter() {
+                if !first {
+                    write!(f, ", ")?;
+                }
+                write!(f, "{}{}", marker, path)?;
+                first = false;
+            }
+        }
+        Ok(())
+    }
+}
+
+/**
+Generates a single [`Integrity`](struct.Integrity.html) for a whole directory
+tree.
+
+The tree is walked in a deterministic order, each file is hashed with
+[`IntegrityOpts`](struct.IntegrityOpts.html), and the root `Integrity` is the
+hash of every file's path, mode and digest. Empty directories aren't
+included, and symlinks aren't followed: their target path is hashed instead.
+Anything that isn't a regular file, directory or symlink, like a FIFO,
+socket or device, is skipped, since reading it could block forever.
+
+# Examples
+
+```no_run
+# use ssri::DirIntegrity;
+let manifest = DirIntegrity::new().manifest("node_modules/lodash")?;
+std::fs::write("lodash.manifest", manifest.to_string()).unwrap();
+
+// Later:
+let expected = std::fs::read_to_string("lodash.manifest").unwrap().parse()?;
+if let Err(err) = DirIntegrity::new().verify("node_modules/lodash", &expected) {
+    eprintln!("{}", err);
+}
+# Ok::<(), ssri::Error>(())
+```
+*/
+#[derive(Clone, Debug)]
+pub struct DirIntegrity {
+    algorithm: Algorithm,
+}
+
+impl Default for DirIntegrity {
+    fn default() -> Self {
+        DirIntegrity {
+            algorithm: Algorithm::Sha256,
+        }
+    }
+}
+
+impl DirIntegrity {
+    /// Creates a new `DirIntegrity` that uses sha256.
+    pub fn new() -> DirIntegrity {
+        Self::default()
+    }
+
+    /// Sets the algorithm used for each file and for the root.
+    ///
+    /// Panics if `algorithm` is an [`Algorithm::Unknown`].
+    pub fn algorithm(mut self, algorithm: Algorithm) -> Self {
+        if matches!(algorithm, Algorithm::Unknown(_)) {
+            panic!("Can't generate hashes for unknown algorithm {}", algorithm);
+        }
+        self.algorithm = algorithm;
+        self
+    }
+
+    /// Walks `dir` and hashes every file in it.
+    pub fn manifest<P: AsRef<Path>>(&self, dir: P) -> Result<DirManifest, Error> {
+        let mut entries = Vec::new();
+        self.walk(dir.as_ref(), "", &mut entries)?;
+        Ok(DirManifest::new(entries, self.algorithm))
+    }
+
+    /// Generates the root `Integrity` for `dir`.
+    pub fn compute<P: AsRef<Path>>(&self, dir: P) -> Result<Integrity, Error> {
+        Ok(self.manifest(dir)?.root)
+    }
+
+    /// Checks `dir` against a root `Integrity`, returning
+    /// [`Error::IntegrityCheckError`] if it doesn't match. Use
+    /// [`DirIntegrity::verify`] to find out which files differ.
+    pub fn check<P: AsRef<Path>>(&self, dir: P, root: &Integrity) -> Result<(), Error> {
+        let actual = self.compute(dir)?;
+        if root.matches(&actual).is_some() {
+            Ok(())
+        } else {
+            Err(Error::IntegrityCheckError(root.clone(), actual))
+        }
+    }
+
+    /// Checks `dir` against an expected manifest, returning
+    /// [`Error::DirIntegrityError`] with the paths that differ if it doesn't
+    /// match.
+    pub fn verify<P: AsRef<Path>>(
+        &self,
+        dir: P,
+        expected: &DirManifest,
+    ) -> Result<DirManifest, Error> {
+        let actual = self.manifest(dir)?;
+        let diff = expected.diff(&actual);
+        if diff.is_empty() {
+            Ok(actual)
+        } else {
+            Err(Error::DirIntegrityError(diff))
+        }
+    }
+
+    fn walk(&self, dir: &Path, prefix: &str, entries: &mut Vec<DirEntry>) -> Result<(), Error> {
+        for child in fs::read_dir(dir)? {
+            let child = child?;
+            let name = child.file_name();
+            let name = name.to_str().ok_or_else(|| {
+                Error::from(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("path is not valid UTF-8: {}", child.path().display()),
+                ))
+            })?;
+            if name.contains('\n') || name.contains('\r') {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("path contains a line break: {}", child.path().display()),
+                )
+                .into());
+            }
+            let path = format!("{}{}", prefix, name);
+            let file_type = child.file_type()?;
+            if file_type.is_dir() {
+                self.walk(&child.path(), &format!("{}/", path), entries)?;
+            } else if file_type.is_symlink() {
+                let target = fs::read_link(child.path())?;
+                entries.push(DirEntry {
+                    path,
+                    mode: MODE_SYMLINK,
+                    integrity: IntegrityOpts::new()
+                        .algorithm(self.algorithm)
+                        .chain(link_target(&target))
+                        .result(),
+                });
+            } else if file_type.is_file() {
+                entries.push(DirEntry {
+                    path,
+                    mode: file_mode(&child.metadata()?),
+                    integrity: IntegrityOpts::new()
+                        .algorithm(self.algorithm)
+                        .read_path(child.path())?
+                        .result(),
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Compares two integrities' digests in constant time.
+fn integrity_eq(a: &Integrity, b: &Integrity) -> bool {
+    a.hashes.len() == b.hashes.len()
+        && a.hashes
+            .iter()
+            .zip(&b.hashes)
+            .fold(true, |eq, (a, b)| a.ct_eq(b) & eq)
+}
+
+/// The bytes of a symlink target, as stored by the OS where possible.
+#[cfg(unix)]
+fn link_target(target: &Path) -> Cow<'_, [u8]> {
+    use std::os::unix::ffi::OsStrExt;
+    Cow::Borrowed(target.as_os_str().as_bytes())
+}
+
+#[cfg(not(unix))]
+fn link_target(target: &Path) -> Cow<'_, [u8]> {
+    Cow::Owned(target.to_string_lossy().into_owned().into_bytes())
+}
+
+#[cfg(unix)]
+fn file_mode(metadata: &fs::Metadata) -> u32 {
+    use std::os::unix::fs::PermissionsExt;
+    if metadata.permissions().mode() & 0o111 != 0 {
+        MODE_EXECUTABLE
+    } else {
+        MODE_FILE
+    }
+}
+
+#[cfg(not(unix))]
+fn file_mode(_metadata: &fs::Metadata) -> u32 {
+    MODE_FILE
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::PathBuf;
+
+    use super::{DirIntegrity, DirManifest};
+    use crate::{Algorithm, Error, Integrity};
+
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> TempDir {
+            let path =
+                std::env::temp_dir().join(format!("ssri-{}-dir-{}", std::process::id(), name));
+            let _ = fs::remove_dir_all(&path);
+            fs::create_dir_all(path.join("lib/nested")).unwrap();
+            fs::write(path.join("package.json"), b"{}").unwrap();
+            fs::write(path.join("lib/index.js"), b"module.exports = 1").unwrap();
+            fs::write(path.join("lib/nested/util.js"), b"exports.x = 2").unwrap();
+            TempDir(path)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn manifest() {
+        let dir = TempDir::new("manifest");
+        let manifest = DirIntegrity::new().manifest(&dir.0).unwrap();
+        let paths = manifest
+            .entries
+            .iter()
+            .map(|e| e.path.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            paths,
+            vec!["lib/index.js", "lib/nested/util.js", "package.json"]
+        );
+        assert_eq!(manifest.entries[2].mode, 0o100644);
+        assert_eq!(manifest.entries[2].integrity, Integrity::from(b"{}"));
+        assert_eq!(manifest.root.pick_algorithm(), Algorithm::Sha256);
+
+        // Stable across runs, and through the string form.
+        assert_eq!(DirIntegrity::new().manifest(&dir.0).unwrap(), manifest);
+        let parsed = manifest.to_string().parse::<DirManifest>().unwrap();
+        assert_eq!(parsed, manifest);
+        assert!(DirIntegrity::new().check(&dir.0, &manifest.root).is_ok());
+    }
+
+    #[test]
+    fn algorithm() {
+        let dir = TempDir::new("algorithm");
+        let manifest = DirIntegrity::new()
+            .algorithm(Algorithm::Sha512)
+            .manifest(&dir.0)
+            .unwrap();
+        assert_eq!(manifest.root.pick_algorithm(), Algorithm::Sha512);
+        assert_ne!(manifest.root, DirIntegrity::new().compute(&dir.0).unwrap());
+        let parsed = manifest.to_string().parse::<DirManifest>().unwrap();
+        assert_eq!(parsed.root, manifest.root);
+    }
+
+    #[test]
+    fn empty_manifest() {
+        let dir = TempDir::new("empty");
+        let empty = dir.0.join("empty");
+        fs::create_dir(&empty).unwrap();
+        let manifest = DirIntegrity::new()
+            .algorithm(Algorithm::Sha512)
+            .manifest(&empty)
+            .unwrap();
+        assert_eq!(manifest.to_string(), "# ssri-dirhash-v1 sha512\n");
+        let parsed = manifest.to_string().parse::<DirManifest>().unwrap();
+        assert_eq!(parsed, manifest);
+
+        for bad in &["", "100644 sha256-AAA= a", "# ssri-dirhash-v1 md5\n"] {
+            assert!(bad.parse::<DirManifest>().is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn reports_differences() {
+        let dir = TempDir::new("differences");
+        let expected = DirIntegrity::new().manifest(&dir.0).unwrap();
+        fs::write(dir.0.join("lib/index.js"), b"module.exports = 2").unwrap();
+        fs::remove_file(dir.0.join("lib/nested/util.js")).unwrap();
+        fs::write(dir.0.join("README.md"), b"# hi").unwrap();
+
+        let result = DirIntegrity::new().check(&dir.0, &expected.root);
+        assert!(matches!(result, Err(Error::IntegrityCheckError(..))));
+        match DirIntegrity::new().verify(&dir.0, &expected) {
+            Err(Error::DirIntegrityError(diff)) => {
+                assert_eq!(diff.added, vec!["README.md"]);
+                assert_eq!(diff.removed, vec!["lib/nested/util.js"]);
+                assert_eq!(diff.changed, vec!["lib/index.js"]);
+                assert_eq!(
+                    diff.to_string(),
+                    "+README.md, -lib/nested/util.js, ~lib/index.js"
+                );
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn modes_and_symlinks() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = TempDir::new("modes");
+        let before = DirIntegrity::new().manifest(&dir.0).unwrap();
+        let script = dir.0.join("package.json");
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).unwrap();
+        std::os::unix::fs::symlink("lib/index.js", dir.0.join("main.js")).unwrap();
+
+        let after = DirIntegrity::new().manifest(&dir.0).unwrap();
+        let diff = before.diff(&after);
+        assert_eq!(diff.changed, vec!["package.json"]);
+        assert_eq!(diff.added, vec!["main.js"]);
+        let link = after.entries.iter().find(|e| e.path == "main.js").unwrap();
+        assert_eq!(link.mode, 0o120000);
+        assert_eq!(link.integrity, Integrity::from(b"lib/index.js"));
+
+        // Targets that aren't UTF-8 are hashed as-is, not lossily.
+        std::os::unix::fs::symlink(OsStr::from_bytes(b"a\xfe"), dir.0.join("fe.js")).unwrap();
+        std::os::unix::fs::symlink(OsStr::from_bytes(b"a\xff"), dir.0.join("ff.js")).unwrap();
+        let manifest = DirIntegrity::new().manifest(&dir.0).unwrap();
+        let fe = manifest.entries.iter().find(|e| e.path == "fe.js").unwrap();
+        let ff = manifest.entries.iter().find(|e| e.path == "ff.js").unwrap();
+        assert_eq!(fe.integrity, Integrity::from(b"a\xfe"));
+        assert_eq!(ff.integrity, Integrity::from(b"a\xff"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn skips_special_files() {
+        let dir = TempDir::new("special");
+        let before = DirIntegrity::new().manifest(&dir.0).unwrap();
+        let _socket = std::os::unix::net::UnixListener::bind(dir.0.join("app.sock")).unwrap();
+        let after = DirIntegrity::new().manifest(&dir.0).unwrap();
+        assert_eq!(before, after);
+    }
+}
@@ -1,10 +1,14 @@
+use std::io;
+use std::sync::Arc;
+
 use miette::Diagnostic;
 use thiserror::Error;
 
-use crate::Integrity;
+use crate::{DirDiff, Integrity};
 
 /// Integrity-related error values.
-#[derive(Diagnostic, Error, Debug, Clone, PartialEq, Eq)]
+#[derive(Diagnostic, Error, Debug, Clone)]
+#[non_exhaustive]
 pub enum Error {
     /// Error parsing an SRI string into an Integrity object.
     #[error("Failed to parse subresource integrity string: {0}")]
@@ -18,4 +22,87 @@ pub enum Error {
     #[error("Failed decode hexadecimal data, reason: {0}")]
     #[diagnostic(code(ssri::hex_decode_error), url(docsrs))]
     HexDecodeError(String),
+    /// A hash's digest was not valid base64, or didn't decode to the number
+    /// of bytes its algorithm produces. Contains the offending hash and the
+    /// reason it was rejected.
+    #[error("Invalid digest in `{0}`: {1}")]
+    #[diagnostic(code(ssri::invalid_digest_error), url(docsrs))]
+    InvalidDigestError(String, String),
+    /// Error registering a user-defined algorithm.
+    #[error("Failed to register algorithm: {0}")]
+    #[diagnostic(code(ssri::algorithm_registration_error), url(docsrs))]
+    AlgorithmRegistrationError(String),
+    /// None of the algorithms in an `Integrity` are allowed by the
+    /// [`VerificationPolicy`](struct.VerificationPolicy.html) it was checked
+    /// with.
+    #[error("None of the algorithms in `{0}` are allowed by the verification policy")]
+    #[diagnostic(code(ssri::algorithm_not_allowed), url(docsrs))]
+    AlgorithmNotAllowed(Integrity),
+    /// More bytes were input than the expected length. Contains the expected
+    /// length and the number of bytes seen when this was noticed.
+    #[error("Expected {0} bytes, but got at least {1}")]
+    #[diagnostic(code(ssri::too_many_bytes), url(docsrs))]
+    TooManyBytes(u64, u64),
+    /// The input ended before reaching the expected length. Contains the
+    /// expected length and the number of bytes actually input.
+    #[error("Expected {0} bytes, but only got {1}")]
+    #[diagnostic(code(ssri::too_few_bytes), url(docsrs))]
+    TooFewBytes(u64, u64),
+    /// A hasher's state couldn't be exported, or an exported state couldn't
+    /// be restored.
+    #[error("Hasher state error: {0}")]
+    #[diagnostic(code(ssri::hasher_state_error), url(docsrs))]
+    HasherStateError(String),
+    /// A directory didn't match its expected manifest. Contains the paths
+    /// that differ.
+    #[error("Directory integrity check failed: {0}")]
+    #[diagnostic(code(ssri::dir_integrity_error), url(docsrs))]
+    DirIntegrityError(DirDiff),
+    /// An I/O error, such as failing to open or read a file. It's shared so
+    /// that `Error` stays `Clone`.
+    #[error("I/O error: {0}")]
+    #[diagnostic(code(ssri::io_error), url(docsrs))]
+    Io(#[source] Arc<io::Error>),
+}
+
+/// I/O errors can't be compared, so they're equal when their kinds and
+/// messages are.
+impl PartialEq for Error {
+    fn eq(&self, other: &Self) -> bool {
+        use Error::*;
+        match (self, other) {
+            (ParseIntegrityError(a), ParseIntegrityError(b)) => a == b,
+            (IntegrityCheckError(a1, a2), IntegrityCheckError(b1, b2)) => a1 == b1 && a2 == b2,
+            (HexDecodeError(a), HexDecodeError(b)) => a == b,
+            (InvalidDigestError(a1, a2), InvalidDigestError(b1, b2)) => a1 == b1 && a2 == b2,
+            (AlgorithmRegistrationError(a), AlgorithmRegistrationError(b)) => a == b,
+            (AlgorithmNotAllowed(a), AlgorithmNotAllowed(b)) => a == b,
+            (TooManyBytes(a1, a2), TooManyBytes(b1, b2)) => a1 == b1 && a2 == b2,
+            (TooFewBytes(a1, a2), TooFewBytes(b1, b2)) => a1 == b1 && a2 == b2,
+            (HasherStateError(a), HasherStateError(b)) => a == b,
+            (DirIntegrityError(a), DirIntegrityError(b)) => a == b,
+            (Io(a), Io(b)) => a.kind() == b.kind() && a.to_string() == b.to_string(),
+            _ => false,
+        }
+    }
+}
+
+impl Eq for Error {}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Error::Io(Arc::new(err))
+    }
+}
+
+/// Lets `Error` flow out through `io::Error` streams and readers. I/O errors
+/// are unwrapped where possible, and everything else becomes an
+/// [`io::ErrorKind::InvalidData`] error wrapping the `Error`.
+impl From<Error> for io::Error {
+    fn from(err: Error) -> Self {
+        match err {
+            Error::Io(io) => Arc::try_unwrap(io).unwrap_or_else(|io| io::Error::new(io.kind(), io)),
+            err => io::Error::new(io::ErrorKind::InvalidData, err),
+        }
+    }
 }
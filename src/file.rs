@@ -0,0 +1,141 @@
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+
+use crate::errors::Error;
+
+/// Size of the buffer used to read files that aren't memory-mapped. Large
+/// enough that the hashers, rather than syscalls, dominate.
+const BUFFER_SIZE: usize = 64 * 1024;
+
+/// Files at least this big are memory-mapped when the `mmap` feature is
+/// enabled. Below it, mapping costs more than it saves.
+#[cfg(feature = "mmap")]
+const MMAP_THRESHOLD: u64 = 1024 * 1024;
+
+/// Feeds the contents of the file at `path` to `input`, returning the number
+/// of bytes read.
+///
+/// With the `mmap` feature, large files are mapped and fed to `input`
+/// `BUFFER_SIZE` bytes at a time. If another process truncates the file
+/// while it's mapped, reading the missing pages raises `SIGBUS`, which kills
+/// the process.
+pub(crate) fn read_path<F: FnMut(&[u8])>(path: &Path, mut input: F) -> Result<u64, Error> {
+    let file = File::open(path)?;
+
+    #[cfg(feature = "mmap")]
+    {
+        let len = file.metadata()?.len();
+        if len >= MMAP_THRESHOLD {
+            // Safety: the mapping is only read from while hashing, and never
+            // outlives `file`. Concurrent writes only change the bytes that get
+            // hashed, but if the file is truncated while mapped, reading past
+            // its new end raises SIGBUS and kills the process. Callers are
+            // warned about this in the docs of the public functions that get
+            // here.
+            if let Ok(map) = unsafe { memmap2::Mmap::map(&file) } {
+                for chunk in map.chunks(BUFFER_SIZE) {
+                    input(chunk);
+                }
+                return Ok(map.len() as u64);
+            }
+        }
+    }
+
+    read_buffered(file, &mut input)
+}
+
+/// Feeds everything read from `reader` to `input`, returning the number of
+/// bytes read.
+pub(crate) fn read_buffered<R: Read, F: FnMut(&[u8])>(
+    mut reader: R,
+    mut input: F,
+) -> Result<u64, Error> {
+    let mut buf = vec![0; BUFFER_SIZE];
+    let mut total = 0;
+    loop {
+        match reader.read(&mut buf) {
+            Ok(0) => return Ok(total),
+            Ok(read) => {
+                input(&buf[..read]);
+                total += read as u64;
+            }
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::PathBuf;
+
+    use super::read_path;
+    use crate::{Algorithm, Error, Integrity, IntegrityOpts};
+
+    fn temp_file(name: &str, data: &[u8]) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("ssri-{}-{}", std::process::id(), name));
+        fs::write(&path, data).unwrap();
+        path
+    }
+
+    #[test]
+    fn small_file() {
+        let path = temp_file("small", b"hello world");
+        let mut data = Vec::new();
+        let read = read_path(&path, |chunk| data.extend_from_slice(chunk)).unwrap();
+        assert_eq!(read, 11);
+        assert_eq!(data, b"hello world");
+        assert_eq!(
+            Integrity::from_path(&path).unwrap(),
+            Integrity::from(b"hello world")
+        );
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn large_file() {
+        // Big enough to be memory-mapped, and not a multiple of the buffer size.
+        let data = (0..3 * 1024 * 1024 + 17)
+            .map(|i| (i % 251) as u8)
+            .collect::<Vec<u8>>();
+        let path = temp_file("large", &data);
+        let sri = IntegrityOpts::new()
+            .algorithm(Algorithm::Sha512)
+            .read_path(&path)
+            .unwrap()
+            .result();
+        assert_eq!(
+            sri,
+            IntegrityOpts::new()
+                .algorithm(Algorithm::Sha512)
+                .chain(&data)
+                .result()
+        );
+        assert_eq!(sri.check_path(&path).unwrap(), Algorithm::Sha512);
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn check_mismatch() {
+        let path = temp_file("mismatch", b"goodbye world");
+        let result = Integrity::from(b"hello world").check_path(&path);
+        assert!(matches!(result, Err(Error::IntegrityCheckError(..))));
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn missing_file() {
+        let path = std::env::temp_dir().join("ssri-does-not-exist");
+        let err = Integrity::from_path(path).unwrap_err();
+        match &err {
+            Error::Io(io) => {
+                assert_eq!(io.kind(), std::io::ErrorKind::NotFound);
+                let source = std::error::Error::source(&err).unwrap();
+                assert_eq!(source.to_string(), io.to_string());
+            }
+            other => panic!("unexpected error: {:?}", other),
+        }
+    }
+}
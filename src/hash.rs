@@ -3,17 +3,344 @@ use std::fmt;
 
 use crate::algorithm::Algorithm;
 use crate::errors::Error;
+use crate::nix;
+
+use base64::prelude::BASE64_STANDARD;
+use base64::Engine as _;
+use subtle::ConstantTimeEq;
+
+/// Largest digest, in bytes, that's stored inline in a [`Hash`]. Every
+/// built-in algorithm fits, so they never allocate. Only user-defined
+/// algorithms with longer digests go on the heap.
+const INLINE_LEN: usize = 64;
+
+#[derive(Clone, PartialEq, Eq, Hash)]
+enum Digest {
+    /// Decoded digest bytes. Every built-in algorithm fits in here.
+    Inline { len: u8, bytes: [u8; INLINE_LEN] },
+    /// Decoded digest bytes too long to fit inline.
+    Heap(Box<[u8]>),
+    /// A digest that isn't valid base64, kept verbatim so it can be
+    /// stringified again.
+    Raw(Box<str>),
+}
+
+impl Digest {
+    fn from_bytes(data: &[u8]) -> Digest {
+        if data.len() <= INLINE_LEN {
+            let mut bytes = [0; INLINE_LEN];
+            bytes[..data.len()].copy_from_slice(data);
+            Digest::Inline {
+                len: data.len() as u8,
+                bytes,
+            }
+        } else {
+            Digest::Heap(data.into())
+        }
+    }
+
+    fn from_base64(digest: &str) -> Digest {
+        match BASE64_STANDARD.decode(digest) {
+            Ok(bytes) => Digest::from_bytes(&bytes),
+            Err(_) => Digest::Raw(digest.into()),
+        }
+    }
+
+    fn as_bytes(&self) -> Option<&[u8]> {
+        match self {
+            Digest::Inline { len, bytes } => Some(&bytes[..*len as usize]),
+            Digest::Heap(bytes) => Some(bytes),
+            Digest::Raw(_) => None,
+        }
+    }
+}
+
+impl fmt::Debug for Digest {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Digest::Raw(digest) => write!(f, "{:?}", digest),
+            _ => write!(f, "{:?}", self.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for Digest {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (self, self.as_bytes()) {
+            (Digest::Raw(digest), _) => f.write_str(digest),
+            (_, Some(bytes)) => f.write_str(&BASE64_STANDARD.encode(bytes)),
+            (_, None) => Ok(()),
+        }
+    }
+}
 
 /**
 Represents a single algorithm/digest pair.
 
 This is mostly internal, although users might interact with it directly on
 occasion.
+
+Digests are stored as raw bytes, inline for every built-in algorithm, and are
+only rendered as base64 when stringified, so hashes of built-in algorithms
+don't allocate unless they have options. Digests that aren't valid base64 are
+still accepted by the parser, and kept verbatim instead.
+
+Any [option expressions](https://w3c.github.io/webappsec-subresource-integrity/#grammardef-option-expression)
+following the digest (`sha256-...?foo?bar`) are kept, and available through
+[`Hash::options`](#method.options), so they survive being parsed and
+stringified again. They're ignored when checking data against a hash, as the
+spec requires.
 */
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct Hash {
     pub algorithm: Algorithm,
-    pub digest: String,
+    digest: Digest,
+    /// Boxed rather than a `Vec`, since most hashes have none: an empty box
+    /// doesn't allocate, and is smaller.
+    options: Box<[String]>,
+}
+
+impl Hash {
+    /// Creates a `Hash` from raw digest bytes.
+    ///
+    /// # Example
+    /// ```
+    /// use ssri::{Algorithm, Hash};
+    ///
+    /// let hash = Hash::from_bytes(Algorithm::Sha1, &[0xde, 0xad, 0xbe, 0xef]);
+    /// assert_eq!(hash.to_string(), "sha1-3q2+7w==");
+    /// assert_eq!(hash.as_bytes(), Some(&[0xde, 0xad, 0xbe, 0xef][..]));
+    /// ```
+    pub fn from_bytes(algorithm: Algorithm, bytes: &[u8]) -> Hash {
+        Hash {
+            algorithm,
+            digest: Digest::from_bytes(bytes),
+            options: Box::default(),
+        }
+    }
+
+    pub(crate) fn from_base64(algorithm: Algorithm, digest: &str) -> Hash {
+        Hash {
+            algorithm,
+            digest: Digest::from_base64(digest),
+            options: Box::default(),
+        }
+    }
+
+    /// Option expressions that followed the digest, without their `?`.
+    ///
+    /// # Example
+    /// ```
+    /// use ssri::Hash;
+    ///
+    /// let hash: Hash = "sha1-Kq5sNclPz7QV2+lfQIuc6R7oRu0=?foo?bar".parse().unwrap();
+    /// assert_eq!(hash.options(), ["foo", "bar"]);
+    /// ```
+    pub fn options(&self) -> &[String] {
+        &self.options
+    }
+
+    pub(crate) fn with_options<'a, I: Iterator<Item = &'a str>>(mut self, options: I) -> Hash {
+        self.options = options.map(String::from).collect();
+        self
+    }
+
+    /// The raw digest bytes, or `None` if the digest this `Hash` was parsed
+    /// from wasn't valid base64.
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        self.digest.as_bytes()
+    }
+
+    /// The digest, as it appears in integrity strings.
+    ///
+    /// # Example
+    /// ```
+    /// use ssri::Hash;
+    ///
+    /// let hash: Hash = "sha1-Kq5sNclPz7QV2+lfQIuc6R7oRu0=?foo".parse().unwrap();
+    /// assert_eq!(hash.digest(), "Kq5sNclPz7QV2+lfQIuc6R7oRu0=");
+    /// ```
+    pub fn digest(&self) -> String {
+        self.digest.to_string()
+    }
+
+    /// Whether `self` and `other` have the same algorithm and digest,
+    /// ignoring any options.
+    ///
+    /// Unlike `==`, the digests are compared in constant time, so this is
+    /// safe to use on attacker-supplied data. Only the algorithms and digest
+    /// lengths, which aren't secret, can short-circuit the comparison. This is
+    /// what [`Integrity::matches`](struct.Integrity.html#method.matches) and
+    /// [`IntegrityChecker`](struct.IntegrityChecker.html) use.
+    ///
+    /// # Example
+    /// ```
+    /// use ssri::Hash;
+    ///
+    /// let hash: Hash = "sha1-Kq5sNclPz7QV2+lfQIuc6R7oRu0=".parse().unwrap();
+    /// let other: Hash = "sha1-Kq5sNclPz7QV2+lfQIuc6R7oRu0=?foo".parse().unwrap();
+    /// assert!(hash.ct_eq(&other));
+    /// ```
+    pub fn ct_eq(&self, other: &Hash) -> bool {
+        if self.algorithm != other.algorithm {
+            return false;
+        }
+        let (this, that) = match (&self.digest, &other.digest) {
+            (Digest::Raw(this), Digest::Raw(that)) => (this.as_bytes(), that.as_bytes()),
+            (this, that) => match (this.as_bytes(), that.as_bytes()) {
+                (Some(this), Some(that)) => (this, that),
+                _ => return false,
+            },
+        };
+        // Slices of different lengths compare unequal right away.
+        this.ct_eq(that).into()
+    }
+
+    /// Checks that the digest is valid base64 and decodes to exactly as many
+    /// bytes as `algorithm` produces. Only the base64 is checked for
+    /// [`Algorithm::Unknown`].
+    ///
+    /// # Example
+    /// ```
+    /// use ssri::Hash;
+    ///
+    /// let hash: Hash = "sha1-Kq5sNclPz7QV2+lfQIuc6R7oRu0=".parse().unwrap();
+    /// assert!(hash.validate().is_ok());
+    /// let hash: Hash = "sha256-deadbeef".parse().unwrap();
+    /// assert!(hash.validate().is_err());
+    /// ```
+    pub fn validate(&self) -> Result<(), Error> {
+        let bytes = match &self.digest {
+            Digest::Raw(digest) => {
+                let err = BASE64_STANDARD
+                    .decode(digest.as_bytes())
+                    .map(|_| String::from("invalid base64"))
+                    .unwrap_or_else(|e| e.to_string());
+                return Err(Error::InvalidDigestError(self.to_string(), err));
+            }
+            digest => digest.as_bytes().unwrap_or_default(),
+        };
+        let expected = self.algorithm.digest_len();
+        if !matches!(self.algorithm, Algorithm::Unknown(_)) && bytes.len() != expected {
+            return Err(Error::InvalidDigestError(
+                self.to_string(),
+                format!(
+                    "expected {} bytes for {}, got {}",
+                    expected,
+                    self.algorithm,
+                    bytes.len()
+                ),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Formats this hash as an
+    /// [OCI digest](https://github.com/opencontainers/image-spec/blob/main/descriptor.md#digests),
+    /// `<algorithm>:<lowercase hex>`, as used to identify container image
+    /// blobs. Options are dropped. The encoding of an unknown algorithm's
+    /// digest can't be known, so it's output exactly as it appears in the
+    /// integrity string, which is how
+    /// [`Integrity::from_oci_digest`](struct.Integrity.html#method.from_oci_digest)
+    /// keeps it.
+    ///
+    /// Fails if the digest doesn't [validate](#method.validate), if the
+    /// algorithm's name isn't allowed in OCI digests, or if an unknown
+    /// algorithm's digest uses characters OCI digests don't allow.
+    ///
+    /// # Example
+    /// ```
+    /// use ssri::Integrity;
+    ///
+    /// let sri = Integrity::from(b"hello");
+    /// assert_eq!(
+    ///     sri.hashes[0].to_oci_digest().unwrap(),
+    ///     "sha256:2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824"
+    /// );
+    /// ```
+    pub fn to_oci_digest(&self) -> Result<String, Error> {
+        let name = self.algorithm.to_string();
+        if !is_oci_algorithm(&name) {
+            return Err(Error::InvalidDigestError(
+                self.to_string(),
+                format!("`{}` isn't a valid OCI digest algorithm", name),
+            ));
+        }
+        if let Algorithm::Unknown(_) = self.algorithm {
+            let digest = self.digest.to_string();
+            if !is_oci_encoded(&digest) {
+                return Err(Error::InvalidDigestError(
+                    self.to_string(),
+                    String::from("expected only characters from [a-zA-Z0-9=_-]"),
+                ));
+            }
+            return Ok(format!("{}:{}", name, digest));
+        }
+        self.validate()?;
+        let bytes = self.as_bytes().unwrap_or_default();
+        Ok(format!("{}:{}", name, hex::encode(bytes)))
+    }
+
+    /// Formats this hash the way Nix prints it by default,
+    /// `<algorithm>:<nix base32>`. Nix's base32 uses its own alphabet and bit
+    /// order, so it isn't interchangeable with RFC 4648 base32. Options are
+    /// dropped.
+    ///
+    /// Fails if the digest doesn't [validate](#method.validate).
+    ///
+    /// # Example
+    /// ```
+    /// use ssri::Hash;
+    ///
+    /// let hash: Hash = "sha1-gA1Zz808BekAy04hS+SPa4hqCN8=".parse().unwrap();
+    /// assert_eq!(hash.to_nix_base32().unwrap(), "sha1:vw46m23bizj4n8afrc0fj19wrp7mj3c0");
+    /// ```
+    pub fn to_nix_base32(&self) -> Result<String, Error> {
+        self.validate()?;
+        let bytes = self.as_bytes().unwrap_or_default();
+        Ok(format!("{}:{}", self.algorithm, nix::encode(bytes)))
+    }
+
+    /// Formats this hash in Nix's `<algorithm>:<hex>` form. Options are
+    /// dropped.
+    ///
+    /// Fails if the digest doesn't [validate](#method.validate).
+    ///
+    /// # Example
+    /// ```
+    /// use ssri::Hash;
+    ///
+    /// let hash: Hash = "sha1-gA1Zz808BekAy04hS+SPa4hqCN8=".parse().unwrap();
+    /// assert_eq!(
+    ///     hash.to_nix_base16().unwrap(),
+    ///     "sha1:800d59cfcd3c05e900cb4e214be48f6b886a08df"
+    /// );
+    /// ```
+    pub fn to_nix_base16(&self) -> Result<String, Error> {
+        self.validate()?;
+        let bytes = self.as_bytes().unwrap_or_default();
+        Ok(format!("{}:{}", self.algorithm, hex::encode(bytes)))
+    }
+}
+
+/// Whether `name` matches the algorithm grammar of
+/// [OCI digests](https://github.com/opencontainers/image-spec/blob/main/descriptor.md#digests):
+/// lowercase alphanumeric components joined by single `+`, `.`, `_` or `-`.
+pub(crate) fn is_oci_algorithm(name: &str) -> bool {
+    name.split(|c| matches!(c, '+' | '.' | '_' | '-'))
+        .all(|part| {
+            !part.is_empty() && part.bytes().all(|b| matches!(b, b'a'..=b'z' | b'0'..=b'9'))
+        })
+}
+
+/// Whether `encoded` matches the encoded part of
+/// [OCI digests](https://github.com/opencontainers/image-spec/blob/main/descriptor.md#digests),
+/// `[a-zA-Z0-9=_-]+`.
+pub(crate) fn is_oci_encoded(encoded: &str) -> bool {
+    !encoded.is_empty()
+        && encoded
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'=' | b'_' | b'-'))
 }
 
 impl PartialOrd for Hash {
@@ -29,7 +356,11 @@ impl Ord for Hash {
 }
 impl fmt::Display for Hash {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}-{}", self.algorithm, self.digest)
+        write!(f, "{}-{}", self.algorithm, self.digest)?;
+        for option in self.options.iter() {
+            write!(f, "?{}", option)?;
+        }
+        Ok(())
     }
 }
 
@@ -38,19 +369,18 @@ impl std::str::FromStr for Hash {
 
     /// Tries to parse a [&str] into a [struct@Hash].
     /// Note the length of the digest is not validated to encode the number of
-    /// bytes expected by the chosen hash algorithm.
+    /// bytes expected by the chosen hash algorithm. Use [`Hash::validate`] to
+    /// check it.
     fn from_str(s: &str) -> Result<Hash, Self::Err> {
-        let mut parsed = s.trim().split(|c| c == '-');
-        let algorithm = parsed
-            .next()
-            .ok_or_else(|| Error::ParseIntegrityError(s.into()))?
-            .parse()?;
-        let digest = String::from(
-            parsed
-                .next()
-                .ok_or_else(|| Error::ParseIntegrityError(s.into()))?,
-        );
-        Ok(Hash { algorithm, digest })
+        let mut parts = s.trim().split('?');
+        let hash = parts.next().unwrap_or_default();
+        // Base64 never contains `-`, but algorithm names like `sha3-256` do,
+        // so split on the last one.
+        let (algorithm, digest) = hash
+            .rsplit_once('-')
+            .ok_or_else(|| Error::ParseIntegrityError(s.into()))?;
+        let algorithm = algorithm.parse()?;
+        Ok(Hash::from_base64(algorithm, digest).with_options(parts))
     }
 }
 
@@ -59,31 +389,80 @@ mod tests {
     use super::Algorithm;
     use super::Hash;
 
+    #[cfg(target_pointer_width = "64")]
+    #[test]
+    fn hash_size() {
+        // 32 for the algorithm, 72 for an inline sha512 digest and 16 for
+        // the options.
+        assert_eq!(std::mem::size_of::<Hash>(), 120);
+        let sha512 = Hash::from_bytes(Algorithm::Sha512, &[7; 64]);
+        assert!(matches!(
+            sha512.digest,
+            super::Digest::Inline { len: 64, .. }
+        ));
+        assert_eq!(sha512.as_bytes(), Some(&[7; 64][..]));
+    }
+
     #[test]
     fn hash_stringify() {
         assert_eq!(
-            format!(
-                "{}",
-                Hash {
-                    algorithm: Algorithm::Sha256,
-                    digest: String::from("deadbeef==")
-                }
-            ),
+            format!("{}", Hash::from_base64(Algorithm::Sha256, "deadbeef==")),
             "sha256-deadbeef=="
+        );
+        assert_eq!(
+            format!("{}", Hash::from_base64(Algorithm::Sha256, "3q2+7w==")),
+            "sha256-3q2+7w=="
         )
     }
 
     #[test]
     fn parsing() {
+        let hash = " sha256-deadbeef== \n".parse::<Hash>().unwrap();
+        assert_eq!(hash, Hash::from_base64(Algorithm::Sha256, "deadbeef=="));
+        assert_eq!(hash.as_bytes(), None);
+        let hash = " sha256-3q2+7w== \n".parse::<Hash>().unwrap();
         assert_eq!(
-            " sha256-deadbeef== \n".parse::<Hash>().unwrap(),
-            Hash {
-                algorithm: Algorithm::Sha256,
-                digest: String::from("deadbeef==")
-            }
+            hash,
+            Hash::from_bytes(Algorithm::Sha256, &[0xde, 0xad, 0xbe, 0xef])
+        );
+    }
+
+    #[test]
+    fn bytes_roundtrip() {
+        let long = [7u8; 100];
+        let hash = Hash::from_bytes(Algorithm::Sha512, &long);
+        assert_eq!(hash.as_bytes(), Some(&long[..]));
+        assert_eq!(hash.to_string().parse::<Hash>().unwrap(), hash);
+
+        let hash: Hash = "sha512-hAAGZT6ayelRF6FckVyquBZikY6SXengBPd0/4LXB5pA1NJ7GzcmV8YdRtRwMEyIx4izpFJ60HTR3MvuXbqpmg=="
+            .parse()
+            .unwrap();
+        assert_eq!(hash.as_bytes().map(|b| b.len()), Some(64));
+        assert_eq!(
+            hash.digest(),
+            "hAAGZT6ayelRF6FckVyquBZikY6SXengBPd0/4LXB5pA1NJ7GzcmV8YdRtRwMEyIx4izpFJ60HTR3MvuXbqpmg=="
+        );
+    }
+
+    #[test]
+    fn parsing_dashed_algorithm() {
+        assert_eq!(
+            "sha3-256-deadbeef==".parse::<Hash>().unwrap(),
+            Hash::from_base64(Algorithm::Sha3_256, "deadbeef==")
         )
     }
 
+    #[test]
+    fn options() {
+        let hash: Hash = "sha256-deadbeef==?foo?ct=text/plain-ish".parse().unwrap();
+        assert_eq!(
+            hash,
+            Hash::from_base64(Algorithm::Sha256, "deadbeef==")
+                .with_options(["foo", "ct=text/plain-ish"].iter().copied())
+        );
+        assert_eq!(hash.to_string(), "sha256-deadbeef==?foo?ct=text/plain-ish");
+    }
+
     #[test]
     #[should_panic]
     fn bad_algorithm() {
@@ -91,54 +470,89 @@ mod tests {
         "sha7-deadbeef==".parse::<Hash>().unwrap();
     }
 
+    #[test]
+    fn validate() {
+        use crate::Error;
+
+        let good: Hash = "sha256-uU0nuZNNPgilLlLX2n2r+sSE7+N6U4DukIj3rOLvzek="
+            .parse()
+            .unwrap();
+        assert_eq!(good.validate(), Ok(()));
+
+        let short: Hash = "sha256-deadbeef".parse().unwrap();
+        assert!(matches!(
+            short.validate(),
+            Err(Error::InvalidDigestError(token, _)) if token == "sha256-deadbeef"
+        ));
+
+        let not_base64: Hash = "sha1-not*base64".parse().unwrap();
+        assert!(matches!(
+            not_base64.validate(),
+            Err(Error::InvalidDigestError(token, _)) if token == "sha1-not*base64"
+        ));
+    }
+
+    #[test]
+    fn oci_digest() {
+        use crate::Error;
+
+        let hash = Hash::from_bytes(Algorithm::Sha512, &[0xab; 64]);
+        assert_eq!(
+            hash.to_oci_digest().unwrap(),
+            format!("sha512:{}", "ab".repeat(64))
+        );
+
+        let short: Hash = "sha256-deadbeef".parse().unwrap();
+        assert!(matches!(
+            short.to_oci_digest(),
+            Err(Error::InvalidDigestError(..))
+        ));
+
+        let unknown: crate::Integrity = "foo_bar-3q2A7w==".parse().unwrap();
+        assert_eq!(
+            unknown.hashes[0].to_oci_digest().unwrap(),
+            "foo_bar:3q2A7w=="
+        );
+        let unknown: crate::Integrity = "foo_bar-3q2+7w==".parse().unwrap();
+        assert!(unknown.hashes[0].to_oci_digest().is_err());
+        let unknown = crate::Integrity::parse_lenient("Foo-3q2+7w==", crate::UnknownTokens::Keep);
+        assert!(unknown.integrity.hashes[0].to_oci_digest().is_err());
+        let unknown = crate::Integrity::parse_lenient("foo-not*base64", crate::UnknownTokens::Keep);
+        assert!(unknown.integrity.hashes[0].to_oci_digest().is_err());
+    }
+
+    #[test]
+    fn ct_eq() {
+        let hash: Hash = "sha256-3q2+7w==".parse().unwrap();
+        assert!(hash.ct_eq(&"sha256-3q2+7w==?opt".parse().unwrap()));
+        assert!(!hash.ct_eq(&"sha256-3q2+7A==".parse().unwrap()));
+        assert!(!hash.ct_eq(&"sha256-3q2+".parse().unwrap()));
+        assert!(!hash.ct_eq(&"sha1-3q2+7w==".parse().unwrap()));
+        assert!(!hash.ct_eq(&"sha256-deadbeef==".parse().unwrap()));
+
+        let raw: Hash = "sha256-deadbeef==".parse().unwrap();
+        assert!(raw.ct_eq(&"sha256-deadbeef==".parse().unwrap()));
+        assert!(!raw.ct_eq(&"sha256-deadbeee==".parse().unwrap()));
+    }
+
     #[test]
     fn ordering() {
         let mut arr = [
-            Hash {
-                algorithm: Algorithm::Sha1,
-                digest: String::from("foo=="),
-            },
-            Hash {
-                algorithm: Algorithm::Sha256,
-                digest: String::from("foo=="),
-            },
-            Hash {
-                algorithm: Algorithm::Sha384,
-                digest: String::from("foo=="),
-            },
-            Hash {
-                algorithm: Algorithm::Sha512,
-                digest: String::from("foo=="),
-            },
-            Hash {
-                algorithm: Algorithm::Xxh3,
-                digest: String::from("foo=="),
-            },
+            Hash::from_base64(Algorithm::Sha1, "foo=="),
+            Hash::from_base64(Algorithm::Sha256, "foo=="),
+            Hash::from_base64(Algorithm::Sha384, "foo=="),
+            Hash::from_base64(Algorithm::Sha512, "foo=="),
+            Hash::from_base64(Algorithm::Xxh3, "foo=="),
         ];
         arr.sort_unstable();
         assert_eq!(
             arr,
             [
-                Hash {
-                    algorithm: Algorithm::Sha512,
-                    digest: String::from("foo==")
-                },
-                Hash {
-                    algorithm: Algorithm::Sha384,
-                    digest: String::from("foo==")
-                },
-                Hash {
-                    algorithm: Algorithm::Sha256,
-                    digest: String::from("foo==")
-                },
-                Hash {
-                    algorithm: Algorithm::Sha1,
-                    digest: String::from("foo==")
-                },
-                Hash {
-                    algorithm: Algorithm::Xxh3,
-                    digest: String::from("foo==")
-                }
+                Hash::from_base64(Algorithm::Sha512, "foo=="),
+                Hash::from_base64(Algorithm::Sha384, "foo=="),
+                Hash::from_base64(Algorithm::Sha256, "foo=="),
+                Hash::from_base64(Algorithm::Sha1, "foo=="),
+                Hash::from_base64(Algorithm::Xxh3, "foo==")
             ]
         )
     }
@@ -0,0 +1,691 @@
+use crate::algorithm::{Algorithm, UnknownAlgorithm};
+use crate::errors::Error;
+use crate::hash::Hash;
+use crate::registry;
+
+/// Algorithms with a name in the IANA "Hash Algorithms for HTTP Digest
+/// Fields" registry. `sha` is SHA-1, and is deprecated.
+const HTTP_NAMES: &[(&str, Algorithm)] = &[
+    ("sha-512", Algorithm::Sha512),
+    ("sha-256", Algorithm::Sha256),
+    ("sha", Algorithm::Sha1),
+];
+
+/// Maps a case-insensitive HTTP digest algorithm name to an `Algorithm`.
+/// Names that aren't in the IANA registry are looked up like integrity
+/// string algorithm names, so registered algorithms like a custom `md5` can
+/// be used. Anything else becomes [`Algorithm::Unknown`], if it fits in one.
+pub(crate) fn algorithm_from_http(name: &str) -> Option<Algorithm> {
+    let name = name.to_ascii_lowercase();
+    HTTP_NAMES
+        .iter()
+        .find(|(http, _)| *http == name)
+        .map(|(_, algorithm)| *algorithm)
+        .or_else(|| registry::lookup(&name))
+        .or_else(|| UnknownAlgorithm::new(&name).map(Algorithm::Unknown))
+}
+
+/// The IANA HTTP digest algorithm name for `algorithm`, if it has one.
+fn http_name(algorithm: Algorithm) -> Option<&'static str> {
+    HTTP_NAMES
+        .iter()
+        .find(|(_, known)| *known == algorithm)
+        .map(|(http, _)| *http)
+}
+
+/// The name to use for `algorithm` in HTTP digest fields: its IANA name, or
+/// the name of a custom or unknown algorithm. Built-in algorithms without an
+/// IANA name have none.
+fn field_name(algorithm: Algorithm) -> Option<String> {
+    match (http_name(algorithm), algorithm) {
+        (Some(http), _) => Some(http.into()),
+        (None, Algorithm::Custom(_)) | (None, Algorithm::Unknown(_)) => Some(algorithm.to_string()),
+        (None, _) => None,
+    }
+}
+
+/// Whether `b` can appear in an HTTP token.
+fn is_tchar(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&b)
+}
+
+/// Whether `name` is a valid HTTP token.
+fn is_token(name: &str) -> bool {
+    !name.is_empty() && name.bytes().all(is_tchar)
+}
+
+/// Trims the optional whitespace HTTP allows around list members.
+fn trim_ows(s: &str) -> &str {
+    s.trim_matches(|c| c == ' ' || c == '\t')
+}
+
+/// Whether `key` is a valid Structured Field key.
+fn is_key(key: &str) -> bool {
+    let mut bytes = key.bytes();
+    matches!(bytes.next(), Some(b'a'..=b'z' | b'*'))
+        && bytes.all(|b| matches!(b, b'a'..=b'z' | b'0'..=b'9' | b'_' | b'-' | b'.' | b'*'))
+}
+
+/// Splits a [Structured Field](https://www.rfc-editor.org/rfc/rfc8941)
+/// dictionary into its keys and raw item values, dropping any parameters.
+/// Members without a value, which are boolean `true`, get `None`. Later
+/// duplicates replace earlier ones.
+fn dictionary(value: &str) -> Result<Vec<(&str, Option<&str>)>, Error> {
+    let mut members: Vec<(&str, Option<&str>)> = Vec::new();
+    let mut parser = FieldParser {
+        value: trim_ows(value),
+        pos: 0,
+    };
+    while !parser.value.is_empty() {
+        let key = parser.key()?;
+        let item = if parser.eat(b'=') {
+            Some(parser.item()?)
+        } else {
+            None
+        };
+        parser.parameters()?;
+        members.retain(|(existing, _)| *existing != key);
+        members.push((key, item));
+        parser.skip_ows();
+        if parser.done() {
+            break;
+        }
+        parser.expect(b',')?;
+        parser.skip_ows();
+        if parser.done() {
+            return Err(parser.invalid());
+        }
+    }
+    Ok(members)
+}
+
+/// Just enough of an RFC 8941 parser to find the members of a dictionary,
+/// so that quoted strings and parameters can contain `,`, `;` and `=`.
+struct FieldParser<'a> {
+    value: &'a str,
+    pos: usize,
+}
+
+impl<'a> FieldParser<'a> {
+    fn invalid(&self) -> Error {
+        Error::ParseIntegrityError(format!(
+            "invalid structured field dictionary `{}`",
+            self.value
+        ))
+    }
+
+    fn done(&self) -> bool {
+        self.pos == self.value.len()
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.value.as_bytes().get(self.pos).copied()
+    }
+
+    fn eat(&mut self, byte: u8) -> bool {
+        let found = self.peek() == Some(byte);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn expect(&mut self, byte: u8) -> Result<(), Error> {
+        if self.eat(byte) {
+            Ok(())
+        } else {
+            Err(self.invalid())
+        }
+    }
+
+    /// Consumes bytes while `pred` holds, returning how many there were.
+    fn take_while(&mut self, pred: impl Fn(u8) -> bool) -> usize {
+        let start = self.pos;
+        while self.peek().map_or(false, &pred) {
+            self.pos += 1;
+        }
+        self.pos - start
+    }
+
+    fn skip_ows(&mut self) {
+        self.take_while(|b| b == b' ' || b == b'\t');
+    }
+
+    fn key(&mut self) -> Result<&'a str, Error> {
+        let start = self.pos;
+        if !matches!(self.peek(), Some(b'a'..=b'z' | b'*')) {
+            return Err(self.invalid());
+        }
+        self.take_while(|b| matches!(b, b'a'..=b'z' | b'0'..=b'9' | b'_' | b'-' | b'.' | b'*'));
+        Ok(&self.value[start..self.pos])
+    }
+
+    /// A bare item or inner list, returned as written.
+    fn item(&mut self) -> Result<&'a str, Error> {
+        let start = self.pos;
+        if self.eat(b'(') {
+            loop {
+                self.take_while(|b| b == b' ');
+                if self.eat(b')') {
+                    break;
+                }
+                self.bare_item()?;
+                self.parameters()?;
+                if !matches!(self.peek(), Some(b' ' | b')')) {
+                    return Err(self.invalid());
+                }
+            }
+        } else {
+            self.bare_item()?;
+        }
+        Ok(&self.value[start..self.pos])
+    }
+
+    fn bare_item(&mut self) -> Result<(), Error> {
+        match self.peek() {
+            Some(b'"') => {
+                self.pos += 1;
+                loop {
+                    match self.peek() {
+                        Some(b'"') => break,
+                        Some(b'\\') => {
+                            self.pos += 1;
+                            if !matches!(self.peek(), Some(b'"' | b'\\')) {
+                                return Err(self.invalid());
+                            }
+                        }
+                        Some(0x20..=0x7e) => {}
+                        _ => return Err(self.invalid()),
+                    }
+                    self.pos += 1;
+                }
+                self.pos += 1;
+            }
+            Some(b':') => {
+                self.pos += 1;
+                self.take_while(|b| b.is_ascii_alphanumeric() || b"+/=".contains(&b));
+                self.expect(b':')?;
+            }
+            Some(b'?') => {
+                self.pos += 1;
+                if !self.eat(b'0') && !self.eat(b'1') {
+                    return Err(self.invalid());
+                }
+            }
+            Some(b'-' | b'0'..=b'9') => {
+                self.eat(b'-');
+                if self.take_while(|b| b.is_ascii_digit()) == 0
+                    || self.eat(b'.') && self.take_while(|b| b.is_ascii_digit()) == 0
+                {
+                    return Err(self.invalid());
+                }
+            }
+            Some(b) if b.is_ascii_alphabetic() || b == b'*' => {
+                self.take_while(|b| is_tchar(b) || b == b':' || b == b'/');
+            }
+            _ => return Err(self.invalid()),
+        }
+        Ok(())
+    }
+
+    fn parameters(&mut self) -> Result<(), Error> {
+        while self.eat(b';') {
+            self.take_while(|b| b == b' ');
+            self.key()?;
+            if self.eat(b'=') {
+                self.bare_item()?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Parses the hashes in an RFC 9530 `Content-Digest` or `Repr-Digest` value.
+pub(crate) fn parse_digest_fields(value: &str) -> Result<Vec<Hash>, Error> {
+    let mut hashes = Vec::new();
+    for (key, item) in dictionary(value)? {
+        let digest = item
+            .and_then(|item| item.strip_prefix(':')?.strip_suffix(':'))
+            .ok_or_else(|| {
+                Error::ParseIntegrityError(format!(
+                    "expected a byte sequence for `{}` in `{}`",
+                    key, value
+                ))
+            })?;
+        if let Some(algorithm) = algorithm_from_http(key) {
+            let hash = Hash::from_base64(algorithm, digest);
+            hash.validate()?;
+            hashes.push(hash);
+        }
+    }
+    Ok(hashes)
+}
+
+/// Formats `hashes` as an RFC 9530 `Content-Digest` or `Repr-Digest` value,
+/// skipping any without an HTTP algorithm name.
+pub(crate) fn format_digest_fields(hashes: &[Hash]) -> Option<String> {
+    let members = hashes
+        .iter()
+        .filter_map(|hash| {
+            let name = field_name(hash.algorithm).filter(|name| is_key(name))?;
+            hash.as_bytes()?;
+            Some(format!("{}=:{}:", name, hash.digest()))
+        })
+        .collect::<Vec<_>>();
+    if members.is_empty() {
+        None
+    } else {
+        Some(members.join(", "))
+    }
+}
+
+/// Parses the hashes in an RFC 3230 `Digest` value. Digests of unknown
+/// algorithms are kept verbatim, since they aren't always base64.
+pub(crate) fn parse_legacy_digest(value: &str) -> Result<Vec<Hash>, Error> {
+    let mut hashes = Vec::new();
+    for member in value.split(',').map(trim_ows).filter(|m| !m.is_empty()) {
+        let (name, digest) = member
+            .split_once('=')
+            .filter(|(name, digest)| is_token(name) && !digest.is_empty())
+            .ok_or_else(|| Error::ParseIntegrityError(format!("invalid Digest `{}`", value)))?;
+        if let Some(algorithm) = algorithm_from_http(name) {
+            let hash = Hash::from_base64(algorithm, digest);
+            if !matches!(algorithm, Algorithm::Unknown(_)) {
+                hash.validate()?;
+            } else if !is_sri_digest(digest) {
+                // An opaque digest that wouldn't parse back out of an
+                // integrity string is dropped like an unknown name.
+                continue;
+            }
+            hashes.push(hash);
+        }
+    }
+    Ok(hashes)
+}
+
+/// Whether an opaque digest survives an integrity string round-trip: it can't
+/// contain the SRI separators `-` and `?`, or whitespace.
+fn is_sri_digest(digest: &str) -> bool {
+    !digest.contains(|c: char| c == '-' || c == '?' || c.is_whitespace())
+}
+
+/// Formats `hashes` as an RFC 3230 `Digest` value, skipping any without an
+/// HTTP algorithm name.
+pub(crate) fn format_legacy_digest(hashes: &[Hash]) -> Option<String> {
+    let members = hashes
+        .iter()
+        .filter_map(|hash| {
+            let name = field_name(hash.algorithm).filter(|name| is_token(name))?;
+            let name = match http_name(hash.algorithm) {
+                Some(_) => name.to_ascii_uppercase(),
+                None => name,
+            };
+            Some(format!("{}={}", name, hash.digest()))
+        })
+        .collect::<Vec<_>>();
+    if members.is_empty() {
+        None
+    } else {
+        Some(members.join(","))
+    }
+}
+
+/// Parses a `Content-MD5` value into an `md5` hash, which is
+/// [`Algorithm::Unknown`] unless an `md5` algorithm has been registered.
+pub(crate) fn parse_content_md5(value: &str) -> Result<Hash, Error> {
+    let algorithm = algorithm_from_http("md5").expect("md5 is a valid algorithm name");
+    let hash = Hash::from_base64(algorithm, trim_ows(value));
+    hash.validate()?;
+    if hash.as_bytes().map(|b| b.len()) != Some(16) {
+        return Err(Error::InvalidDigestError(
+            hash.to_string(),
+            String::from("expected 16 bytes for md5"),
+        ));
+    }
+    Ok(hash)
+}
+
+/// Formats the first 16-byte `md5` hash in `hashes` as a `Content-MD5` value.
+pub(crate) fn format_content_md5(hashes: &[Hash]) -> Option<String> {
+    hashes
+        .iter()
+        .find(|hash| {
+            hash.algorithm.to_string() == "md5" && hash.as_bytes().map(|b| b.len()) == Some(16)
+        })
+        .map(|hash| hash.digest())
+}
+
+/// Parses an RFC 3230 qvalue into thousandths.
+fn parse_qvalue(q: &str) -> Option<u16> {
+    let (int, frac) = q.split_once('.').unwrap_or((q, ""));
+    if frac.len() > 3 || !frac.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let frac = format!("{:0<3}", frac).parse::<u16>().ok()?;
+    match int {
+        "0" => Some(frac),
+        "1" if frac == 0 => Some(1000),
+        _ => None,
+    }
+}
+
+/**
+Digest algorithm preferences sent by an HTTP client, used to pick which
+algorithm a response's digest should use.
+
+Weights range from `0`, meaning "not acceptable", to `1000`. RFC 9530's
+integer weights from `0` to `10` are scaled up to match, and RFC 3230
+qvalues are kept in thousandths. Algorithms `ssri`
+doesn't support are kept as [`Algorithm::Unknown`](enum.Algorithm.html#variant.Unknown).
+
+# Examples
+
+```
+# use ssri::{Algorithm, DigestPreferences, Integrity, IntegrityOpts};
+let prefs = DigestPreferences::from_want_content_digest("sha-512=3, sha-256=10, unixsum=0")?;
+let sri = IntegrityOpts::new()
+    .algorithm(Algorithm::Sha512)
+    .algorithm(Algorithm::Sha256)
+    .chain(b"hello")
+    .result();
+let algorithm = prefs.pick(sri.hashes.iter().map(|h| h.algorithm));
+assert_eq!(algorithm, Some(Algorithm::Sha256));
+# Ok::<(), ssri::Error>(())
+```
+*/
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DigestPreferences {
+    /// Each requested algorithm with its weight, in header order.
+    pub weights: Vec<(Algorithm, u16)>,
+}
+
+impl DigestPreferences {
+    /// Parses an RFC 9530 `Want-Content-Digest` or `Want-Repr-Digest` value,
+    /// like `sha-512=3, sha-256=10`.
+    pub fn from_want_content_digest(value: &str) -> Result<DigestPreferences, Error> {
+        let mut weights = Vec::new();
+        for (key, item) in dictionary(value)? {
+            let weight = item
+                .filter(|item| item.len() <= 2 && item.bytes().all(|b| b.is_ascii_digit()))
+                .and_then(|item| item.parse::<u16>().ok())
+                .filter(|weight| *weight <= 10)
+                .ok_or_else(|| {
+                    Error::ParseIntegrityError(format!(
+                        "expected a weight from 0 to 10 for `{}` in `{}`",
+                        key, value
+                    ))
+                })?;
+            if let Some(algorithm) = algorithm_from_http(key) {
+                weights.push((algorithm, weight * 100));
+            }
+        }
+        Ok(DigestPreferences { weights })
+    }
+
+    /// Parses a legacy RFC 3230 `Want-Digest` value, like
+    /// `SHA-512;q=0.3, sha-256;q=1`. Algorithm names are case-insensitive,
+    /// and ones without a `q` parameter get a weight of `1000`.
+    pub fn from_want_digest(value: &str) -> Result<DigestPreferences, Error> {
+        let invalid = || Error::ParseIntegrityError(format!("invalid Want-Digest `{}`", value));
+        let mut weights = Vec::new();
+        for member in value.split(',').map(trim_ows).filter(|m| !m.is_empty()) {
+            let mut params = member.split(';').map(trim_ows);
+            let name = params.next().unwrap_or_default();
+            if !is_token(name) {
+                return Err(invalid());
+            }
+            let mut weight = 1000;
+            for param in params {
+                let (key, q) = param.split_once('=').ok_or_else(invalid)?;
+                if trim_ows(key).eq_ignore_ascii_case("q") {
+                    weight = parse_qvalue(trim_ows(q)).ok_or_else(invalid)?;
+                }
+            }
+            if let Some(algorithm) = algorithm_from_http(name) {
+                weights.push((algorithm, weight));
+            }
+        }
+        Ok(DigestPreferences { weights })
+    }
+
+    /// The weight given to `algorithm`, or `None` if it wasn't mentioned.
+    pub fn weight(&self, algorithm: Algorithm) -> Option<u16> {
+        self.weights
+            .iter()
+            .find(|(wanted, _)| *wanted == algorithm)
+            .map(|(_, weight)| *weight)
+    }
+
+    /// Picks the most preferred of the `available` algorithms. Algorithms
+    /// that weren't mentioned, or have a weight of `0`, are never picked.
+    /// Ties go to the stronger algorithm.
+    pub fn pick<I: IntoIterator<Item = Algorithm>>(&self, available: I) -> Option<Algorithm> {
+        available
+            .into_iter()
+            .filter_map(|algorithm| {
+                self.weight(algorithm)
+                    .filter(|weight| *weight > 0)
+                    .map(|weight| (weight, algorithm))
+            })
+            .max_by(|(a_weight, a), (b_weight, b)| a_weight.cmp(b_weight).then(b.cmp(a)))
+            .map(|(_, algorithm)| algorithm)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{dictionary, parse_qvalue, DigestPreferences};
+    use crate::{Algorithm, Error, Integrity, IntegrityOpts};
+
+    // From RFC 9530, section 3.
+    const SHA256: &str = "sha-256=:X48E9qOokqqrvdts8nOJRJN3OWDUoyWxBf7kbu9DBPE=:";
+    const SHA512: &str = "sha-512=:WZDPaVn/7XgHaAy8pmojAkGWoRx2UFChF41A2svX+TaPm+AbwAgBWnrIiYllu7BNNyealdVLvRwEmTHWXvJwew==:";
+
+    #[test]
+    fn content_digest() {
+        let body = br#"{"hello": "world"}"#;
+        let sri = Integrity::from_content_digest(SHA256).unwrap();
+        assert_eq!(sri, Integrity::from(body));
+        assert_eq!(sri.to_content_digest().unwrap(), SHA256);
+
+        let both = format!("{},\t{};foo=1", SHA256, SHA512);
+        let sri = Integrity::from_content_digest(&both).unwrap();
+        let expected = IntegrityOpts::new()
+            .algorithm(Algorithm::Sha256)
+            .algorithm(Algorithm::Sha512)
+            .chain(body)
+            .result();
+        assert_eq!(sri, expected);
+        assert_eq!(sri.check(body).unwrap(), Algorithm::Sha512);
+        assert_eq!(
+            sri.to_content_digest().unwrap(),
+            format!("{}, {}", SHA512, SHA256)
+        );
+
+        let sri = Integrity::from_content_digest("unixsum=:AAA=:, sha-256=:AAA=:, sha-256=:X48E9qOokqqrvdts8nOJRJN3OWDUoyWxBf7kbu9DBPE=:").unwrap();
+        assert_eq!(
+            sri.to_string(),
+            "sha256-X48E9qOokqqrvdts8nOJRJN3OWDUoyWxBf7kbu9DBPE= unixsum-AAA="
+        );
+        assert_eq!(
+            sri.to_content_digest().unwrap(),
+            format!("{}, unixsum=:AAA=:", SHA256)
+        );
+        assert_eq!(sri.to_string().parse::<Integrity>().unwrap(), sri);
+        assert_eq!(
+            Integrity::from_content_digest(&sri.to_content_digest().unwrap()).unwrap(),
+            sri
+        );
+
+        let params = format!(r#"{};note="a, b; c=d";n=1.5, unixsum=:AAA=:;x=?1"#, SHA256);
+        assert_eq!(Integrity::from_content_digest(&params).unwrap(), sri);
+
+        let xxh3 = IntegrityOpts::new()
+            .algorithm(Algorithm::Xxh3)
+            .chain(body)
+            .result();
+        assert_eq!(xxh3.to_content_digest(), None);
+    }
+
+    #[test]
+    fn invalid_content_digest() {
+        for bad in &[
+            "",
+            "sha-256",
+            "sha-256=X48E9qOokqqrvdts8nOJRJN3OWDUoyWxBf7kbu9DBPE=",
+            "sha-256=:X48E9qOokqqrvdts8nOJRJN3OWDUoyWxBf7kbu9DBPE=:,",
+            "SHA-256=:X48E9qOokqqrvdts8nOJRJN3OWDUoyWxBf7kbu9DBPE=:",
+        ] {
+            assert!(Integrity::from_content_digest(bad).is_err(), "{}", bad);
+        }
+        assert!(matches!(
+            Integrity::from_content_digest("sha-256=:AAA=:"),
+            Err(Error::InvalidDigestError(..))
+        ));
+    }
+
+    #[test]
+    fn structured_field_dictionary() {
+        assert_eq!(dictionary(" ").unwrap(), vec![]);
+        assert_eq!(
+            dictionary(r#"a=1;q="x,y;z=\"w\"", b, c=("a b" tok);p=:AA==:, a=*t/k:1"#).unwrap(),
+            vec![
+                ("b", None),
+                ("c", Some(r#"("a b" tok)"#)),
+                ("a", Some("*t/k:1")),
+            ]
+        );
+        assert_eq!(
+            dictionary("a=?0;b, d=-2.5\t,e=\"\"").unwrap(),
+            vec![("a", Some("?0")), ("d", Some("-2.5")), ("e", Some("\"\""))]
+        );
+        for bad in &[
+            "a,",
+            "a=",
+            "A=1",
+            "a=1 b=2",
+            r#"a="unterminated"#,
+            r#"a="bad \e""#,
+            "a=:AA==",
+            "a=?2",
+            "a=1.",
+            "a=1;",
+            "a=(1 2",
+            "a=1,,b=2",
+        ] {
+            assert!(dictionary(bad).is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn want_content_digest() {
+        let prefs = DigestPreferences::from_want_content_digest("sha-512=3, sha-256=10, unixsum=0")
+            .unwrap();
+        assert_eq!(prefs.weight(Algorithm::Sha512), Some(300));
+        assert_eq!(prefs.weight(Algorithm::Sha256), Some(1000));
+        assert_eq!(prefs.weight(Algorithm::Sha1), None);
+        assert!(matches!(
+            prefs.weights[2].0,
+            Algorithm::Unknown(unknown) if unknown.as_str() == "unixsum"
+        ));
+
+        let all = [Algorithm::Sha512, Algorithm::Sha256, Algorithm::Sha1];
+        assert_eq!(prefs.pick(all.iter().copied()), Some(Algorithm::Sha256));
+        assert_eq!(prefs.pick(vec![Algorithm::Sha512]), Some(Algorithm::Sha512));
+        assert_eq!(prefs.pick(vec![Algorithm::Sha1]), None);
+        assert_eq!(prefs.pick(vec![prefs.weights[2].0]), None);
+
+        let tied = DigestPreferences::from_want_content_digest("sha-256=5, sha-512=5").unwrap();
+        assert_eq!(tied.pick(all.iter().copied()), Some(Algorithm::Sha512));
+
+        for bad in &[
+            "sha-256=11",
+            "sha-256=-1",
+            "sha-256=+1",
+            "sha-256",
+            "sha-256=0.5",
+        ] {
+            assert!(
+                DigestPreferences::from_want_content_digest(bad).is_err(),
+                "{}",
+                bad
+            );
+        }
+    }
+
+    #[test]
+    fn legacy_digest() {
+        let sri = Integrity::from_digest_header(
+            "SHA-256=uU0nuZNNPgilLlLX2n2r+sSE7+N6U4DukIj3rOLvzek=, sha=Kq5sNclPz7QV2+lfQIuc6R7oRu0=,MD5=XrY7u+Ae7tCTyyK7j1rNww==,UNIXsum=30637",
+        )
+        .unwrap();
+        assert_eq!(
+            sri.to_string(),
+            "sha256-uU0nuZNNPgilLlLX2n2r+sSE7+N6U4DukIj3rOLvzek= sha1-Kq5sNclPz7QV2+lfQIuc6R7oRu0= md5-XrY7u+Ae7tCTyyK7j1rNww== unixsum-30637"
+        );
+        assert_eq!(sri.check(b"hello world").unwrap(), Algorithm::Sha256);
+        assert_eq!(
+            sri.to_digest_header().unwrap(),
+            "SHA-256=uU0nuZNNPgilLlLX2n2r+sSE7+N6U4DukIj3rOLvzek=,SHA=Kq5sNclPz7QV2+lfQIuc6R7oRu0=,md5=XrY7u+Ae7tCTyyK7j1rNww==,unixsum=30637"
+        );
+        assert_eq!(
+            Integrity::from_digest_header(&sri.to_digest_header().unwrap()).unwrap(),
+            sri
+        );
+        assert_eq!(sri.to_string().parse::<Integrity>().unwrap(), sri);
+        // Opaque digests that an integrity string can't hold are dropped.
+        let opaque = Integrity::from_digest_header(
+            "UNIXsum=30-637, foo=a?b, sha=Kq5sNclPz7QV2+lfQIuc6R7oRu0=",
+        )
+        .unwrap();
+        assert_eq!(opaque.to_string(), "sha1-Kq5sNclPz7QV2+lfQIuc6R7oRu0=");
+        // Opaque digests can't go in RFC 9530 byte sequences.
+        assert!(!sri.to_content_digest().unwrap().contains("unixsum"));
+
+        assert_eq!(sri.to_content_md5().unwrap(), "XrY7u+Ae7tCTyyK7j1rNww==");
+        let md5 = Integrity::from_content_md5(" XrY7u+Ae7tCTyyK7j1rNww==").unwrap();
+        assert_eq!(md5.to_string(), "md5-XrY7u+Ae7tCTyyK7j1rNww==");
+        assert_eq!(Integrity::from(b"hello world").to_content_md5(), None);
+
+        for bad in &["", "SHA-256", "SHA-256=", "SHA-256=AAA=", "=AAA="] {
+            assert!(Integrity::from_digest_header(bad).is_err(), "{}", bad);
+        }
+        for bad in &["", "AAA=", "Kq5sNclPz7QV2+lfQIuc6R7oRu0=", "not base64"] {
+            assert!(Integrity::from_content_md5(bad).is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn want_digest() {
+        let prefs =
+            DigestPreferences::from_want_digest("SHA-512;q=0.3, sha-256;Q=1, MD5;q=0.25,, SHA")
+                .unwrap();
+        assert_eq!(prefs.weight(Algorithm::Sha512), Some(300));
+        assert_eq!(prefs.weight(Algorithm::Sha256), Some(1000));
+        assert_eq!(prefs.weight(Algorithm::Sha1), Some(1000));
+        assert!(matches!(
+            prefs.weights[2],
+            (Algorithm::Unknown(unknown), 250) if unknown.as_str() == "md5"
+        ));
+        assert_eq!(
+            prefs.pick(vec![Algorithm::Sha512, Algorithm::Sha256, Algorithm::Sha1]),
+            Some(Algorithm::Sha256)
+        );
+
+        for (q, weight) in &[
+            ("0", 0),
+            ("0.", 0),
+            ("0.5", 500),
+            ("0.125", 125),
+            ("1.000", 1000),
+        ] {
+            assert_eq!(parse_qvalue(q), Some(*weight), "{}", q);
+        }
+        for q in &["", "2", "1.5", "0.1234", "-0", ".5", "0.x"] {
+            assert_eq!(parse_qvalue(q), None, "{}", q);
+        }
+        assert!(DigestPreferences::from_want_digest("sha-256;q=2").is_err());
+        assert!(DigestPreferences::from_want_digest("sha-256;q").is_err());
+        assert!(DigestPreferences::from_want_digest("sha 256").is_err());
+    }
+}
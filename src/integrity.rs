@@ -1,12 +1,15 @@
 use std::fmt;
+use std::path::Path;
 
-use crate::algorithm::Algorithm;
+use crate::algorithm::{Algorithm, UnknownAlgorithm};
 use crate::checker::IntegrityChecker;
 use crate::errors::Error;
-use crate::hash::Hash;
+use crate::file;
+use crate::hash::{self, Hash};
+use crate::http;
+use crate::nix;
 use crate::opts::IntegrityOpts;
-
-use base64::Engine as _;
+use crate::policy::VerificationPolicy;
 
 #[cfg(feature = "serde")]
 use serde::de::{self, Deserialize, Deserializer, Visitor};
@@ -29,6 +32,21 @@ let source = "sha256-uU0nuZNNPgilLlLX2n2r+sSE7+N6U4DukIj3rOLvzek=";
 let parsed: Integrity = source.parse().unwrap();
 assert_eq!(parsed.to_string(), source);
 ```
+
+Tokens using an algorithm `ssri` doesn't know about are kept as opaque
+[`Algorithm::Unknown`](enum.Algorithm.html#variant.Unknown) hashes, so
+anything [`Integrity::parse_lenient`](#method.parse_lenient) keeps, or
+`Display` writes, can be parsed back. They're never used to check data. Use
+[`Integrity::parse_strict`](#method.parse_strict) to reject them.
+
+```
+# use ssri::{Algorithm, Integrity};
+let parsed: Integrity = "sha256-uU0nuZNNPgilLlLX2n2r+sSE7+N6U4DukIj3rOLvzek= md5-XrY7u+Ae7tCTyyK7j1rNww=="
+    .parse()
+    .unwrap();
+assert!(matches!(parsed.hashes[1].algorithm, Algorithm::Unknown(_)));
+assert_eq!(parsed.check(b"hello world").unwrap(), Algorithm::Sha256);
+```
 */
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct Integrity {
@@ -63,13 +81,37 @@ impl std::str::FromStr for Integrity {
     fn from_str(s: &str) -> Result<Integrity, Self::Err> {
         let mut hashes = String::from(s)
             .split_whitespace()
-            .map(|x| x.parse())
+            .map(|x| {
+                x.parse::<Hash>()
+                    .or_else(|err| Integrity::parse_unknown(x).ok_or(err))
+            })
             .collect::<Result<Vec<Hash>, Self::Err>>()?;
         hashes.sort();
         Ok(Integrity { hashes })
     }
 }
 
+/// What [`Integrity::parse_lenient`] does with tokens whose algorithm isn't
+/// built in or registered.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UnknownTokens {
+    /// Leave them out of the parsed `Integrity`, like browsers do.
+    Drop,
+    /// Keep them as [`Algorithm::Unknown`] entries, so they survive being
+    /// stringified again.
+    Keep,
+}
+
+/// The result of [`Integrity::parse_lenient`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseReport {
+    /// Everything that could be parsed.
+    pub integrity: Integrity,
+    /// Tokens that were left out of `integrity`, as they appeared in the
+    /// source string.
+    pub skipped: Vec<String>,
+}
+
 #[cfg(feature = "serde")]
 impl Serialize for Integrity {
     fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
@@ -108,6 +150,78 @@ impl<'de> Deserialize<'de> for Integrity {
 }
 
 impl Integrity {
+    /// Parses a string into an `Integrity`, like [`str::parse`], but also
+    /// checks that every digest is valid base64 of the right length for its
+    /// algorithm, and rejects algorithms `ssri` doesn't know about.
+    ///
+    /// # Example
+    /// ```
+    /// use ssri::{Error, Integrity};
+    ///
+    /// let sri = Integrity::parse_strict("sha1-Kq5sNclPz7QV2+lfQIuc6R7oRu0=").unwrap();
+    /// assert_eq!(sri.to_string(), "sha1-Kq5sNclPz7QV2+lfQIuc6R7oRu0=");
+    ///
+    /// let err = Integrity::parse_strict("sha256-deadbeef").unwrap_err();
+    /// assert!(matches!(err, Error::InvalidDigestError(..)));
+    /// ```
+    pub fn parse_strict(s: &str) -> Result<Integrity, Error> {
+        let sri: Integrity = s.parse()?;
+        for hash in &sri.hashes {
+            if matches!(hash.algorithm, Algorithm::Unknown(_)) {
+                return Err(Error::ParseIntegrityError(hash.to_string()));
+            }
+            hash.validate()?;
+        }
+        Ok(sri)
+    }
+
+    /// Parses a string into an `Integrity` the way browsers do: tokens that
+    /// can't be parsed, or that use an algorithm `ssri` doesn't know about,
+    /// don't fail the whole string. Malformed tokens are always skipped, and
+    /// `unknown` decides what happens to unknown algorithms.
+    ///
+    /// # Example
+    /// ```
+    /// use ssri::{Integrity, UnknownTokens};
+    ///
+    /// let source = "md5-deadbeef sha256-badc0ffee garbage";
+    /// let report = Integrity::parse_lenient(source, UnknownTokens::Drop);
+    /// assert_eq!(report.integrity.to_string(), "sha256-badc0ffee");
+    /// assert_eq!(report.skipped, vec!["md5-deadbeef", "garbage"]);
+    ///
+    /// let report = Integrity::parse_lenient(source, UnknownTokens::Keep);
+    /// assert_eq!(report.integrity.to_string(), "sha256-badc0ffee md5-deadbeef");
+    /// assert_eq!(report.skipped, vec!["garbage"]);
+    /// ```
+    pub fn parse_lenient(s: &str, unknown: UnknownTokens) -> ParseReport {
+        let mut hashes = Vec::new();
+        let mut skipped = Vec::new();
+        for token in s.split_whitespace() {
+            match token.parse::<Hash>() {
+                Ok(hash) => hashes.push(hash),
+                Err(_) => match (unknown, Self::parse_unknown(token)) {
+                    (UnknownTokens::Keep, Some(hash)) => hashes.push(hash),
+                    _ => skipped.push(token.into()),
+                },
+            }
+        }
+        hashes.sort();
+        ParseReport {
+            integrity: Integrity { hashes },
+            skipped,
+        }
+    }
+
+    fn parse_unknown(token: &str) -> Option<Hash> {
+        let mut parts = token.split('?');
+        let (algorithm, digest) = parts.next()?.rsplit_once('-')?;
+        if digest.is_empty() {
+            return None;
+        }
+        let algorithm = Algorithm::Unknown(UnknownAlgorithm::new(algorithm)?);
+        Some(Hash::from_base64(algorithm, digest).with_options(parts))
+    }
+
     /// Pick the most secure available `Algorithm` in this `Integrity`.
     ///
     /// # Example
@@ -140,6 +254,9 @@ impl Integrity {
 
     /// Converts a hex string obtained from `to_hex()` to an `Integrity` with a `Hash` containing algorithm and decoded hex string.
     ///
+    /// Returns [`Error::InvalidDigestError`](enum.Error.html#variant.InvalidDigestError)
+    /// if the hex doesn't decode to as many bytes as `algorithm` produces.
+    ///
     /// # Example
     ///```
     /// use ssri::{Integrity, Algorithm};
@@ -150,12 +267,288 @@ impl Integrity {
     ///```
     pub fn from_hex<B: AsRef<[u8]>>(hex: B, algorithm: Algorithm) -> Result<Integrity, Error> {
         let b16 = hex::decode(hex).map_err(|e| Error::HexDecodeError(e.to_string()))?;
-        let digest = base64::prelude::BASE64_STANDARD.encode(b16);
+        let hash = Hash::from_bytes(algorithm, &b16);
+        hash.validate()?;
+        Ok(Integrity { hashes: vec![hash] })
+    }
+
+    /// Parses an
+    /// [OCI digest](https://github.com/opencontainers/image-spec/blob/main/descriptor.md#digests),
+    /// `<algorithm>:<hex>`, as used by container tooling to identify blobs.
+    ///
+    /// For algorithms `ssri` knows, the encoded part must be lowercase hex,
+    /// exactly as long as the algorithm's digest. Other algorithms are kept
+    /// as [`Algorithm::Unknown`](enum.Algorithm.html#variant.Unknown), as
+    /// long as their name fits one, and since their encoding can't be known,
+    /// the encoded part is kept verbatim. It only has to match the OCI
+    /// grammar, `[a-zA-Z0-9=_-]+`, without `-`, which integrity strings can't
+    /// hold in a digest. It's output as-is by
+    /// [`Hash::to_oci_digest`](struct.Hash.html#method.to_oci_digest), and
+    /// survives being stringified and parsed again.
+    ///
+    /// # Example
+    /// ```
+    /// use ssri::{Error, Integrity};
+    ///
+    /// let sri = Integrity::from_oci_digest(
+    ///     "sha256:2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824",
+    /// ).unwrap();
+    /// assert_eq!(sri, Integrity::from(b"hello"));
+    ///
+    /// let err = Integrity::from_oci_digest("sha256:2cf24dba").unwrap_err();
+    /// assert!(matches!(err, Error::InvalidDigestError(..)));
+    ///
+    /// let sri = Integrity::from_oci_digest("multihash_base58:QmRZxt2b1FVZPNqd8hsiykDL3TdBDeTSPX9Kv46HmX4Gx8").unwrap();
+    /// assert_eq!(
+    ///     sri.hashes[0].to_oci_digest().unwrap(),
+    ///     "multihash_base58:QmRZxt2b1FVZPNqd8hsiykDL3TdBDeTSPX9Kv46HmX4Gx8"
+    /// );
+    /// ```
+    pub fn from_oci_digest(digest: &str) -> Result<Integrity, Error> {
+        let (name, encoded) = digest
+            .split_once(':')
+            .filter(|(name, _)| hash::is_oci_algorithm(name))
+            .ok_or_else(|| Error::ParseIntegrityError(digest.into()))?;
+        let algorithm = match name.parse::<Algorithm>() {
+            Ok(algorithm) => algorithm,
+            Err(_) => {
+                let algorithm = UnknownAlgorithm::new(name)
+                    .ok_or_else(|| Error::ParseIntegrityError(digest.into()))?;
+                if !hash::is_oci_encoded(encoded) {
+                    return Err(Error::InvalidDigestError(
+                        digest.into(),
+                        String::from("expected only characters from [a-zA-Z0-9=_-]"),
+                    ));
+                }
+                // Integrity strings split the algorithm off at the last `-`.
+                if encoded.contains('-') {
+                    return Err(Error::InvalidDigestError(
+                        digest.into(),
+                        String::from("`-` is unsupported in digests of unknown algorithms"),
+                    ));
+                }
+                return Ok(Integrity {
+                    hashes: vec![Hash::from_base64(Algorithm::Unknown(algorithm), encoded)],
+                });
+            }
+        };
+        if encoded.is_empty()
+            || !encoded
+                .bytes()
+                .all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+        {
+            return Err(Error::InvalidDigestError(
+                digest.into(),
+                format!("only lowercase hex is supported for {}", algorithm),
+            ));
+        }
+        if encoded.len() != algorithm.digest_len() * 2 {
+            return Err(Error::InvalidDigestError(
+                digest.into(),
+                format!(
+                    "expected {} hex characters for {}, got {}",
+                    algorithm.digest_len() * 2,
+                    algorithm,
+                    encoded.len()
+                ),
+            ));
+        }
+        let bytes = hex::decode(encoded).map_err(|e| Error::HexDecodeError(e.to_string()))?;
+        Ok(Integrity {
+            hashes: vec![Hash::from_bytes(algorithm, &bytes)],
+        })
+    }
+
+    /// Converts a digest in Nix's base32 encoding, as found in the `sha256`
+    /// attribute of Nix fetchers, to an `Integrity` using `algorithm`.
+    ///
+    /// # Example
+    /// ```
+    /// use ssri::{Algorithm, Integrity};
+    ///
+    /// let sri = Integrity::from_nix_base32("vw46m23bizj4n8afrc0fj19wrp7mj3c0", Algorithm::Sha1).unwrap();
+    /// assert_eq!(sri.to_string(), "sha1-gA1Zz808BekAy04hS+SPa4hqCN8=");
+    /// ```
+    pub fn from_nix_base32<S: AsRef<str>>(
+        digest: S,
+        algorithm: Algorithm,
+    ) -> Result<Integrity, Error> {
+        let digest = digest.as_ref();
+        let bytes = nix::decode(digest, algorithm.digest_len())
+            .map_err(|reason| Error::InvalidDigestError(digest.into(), reason))?;
         Ok(Integrity {
-            hashes: vec![Hash { algorithm, digest }],
+            hashes: vec![Hash::from_bytes(algorithm, &bytes)],
         })
     }
 
+    /// Parses a hash in any of the forms Nix accepts: `<algorithm>:<digest>`,
+    /// where the digest is base16, Nix base32 or base64 depending on its
+    /// length, or a single SRI hash like `sha256-<base64>`.
+    ///
+    /// # Example
+    /// ```
+    /// use ssri::Integrity;
+    ///
+    /// let expected: Integrity = "sha1-gA1Zz808BekAy04hS+SPa4hqCN8=".parse().unwrap();
+    /// for hash in &[
+    ///     "sha1:800d59cfcd3c05e900cb4e214be48f6b886a08df",
+    ///     "sha1:vw46m23bizj4n8afrc0fj19wrp7mj3c0",
+    ///     "sha1:gA1Zz808BekAy04hS+SPa4hqCN8=",
+    ///     "sha1-gA1Zz808BekAy04hS+SPa4hqCN8=",
+    /// ] {
+    ///     assert_eq!(Integrity::from_nix_hash(hash).unwrap(), expected);
+    /// }
+    /// ```
+    pub fn from_nix_hash(s: &str) -> Result<Integrity, Error> {
+        let s = s.trim();
+        let (name, digest) = match s.split_once(':') {
+            Some(parts) => parts,
+            None => return Integrity::parse_strict(s),
+        };
+        let algorithm: Algorithm = name.parse()?;
+        let len = algorithm.digest_len();
+        let hash = if digest.len() == len * 2 {
+            let bytes = hex::decode(digest).map_err(|e| Error::HexDecodeError(e.to_string()))?;
+            Hash::from_bytes(algorithm, &bytes)
+        } else if digest.len() == nix::encoded_len(len) {
+            return Integrity::from_nix_base32(digest, algorithm);
+        } else if digest.len() == (len + 2) / 3 * 4 {
+            let hash = Hash::from_base64(algorithm, digest);
+            hash.validate()?;
+            hash
+        } else {
+            return Err(Error::InvalidDigestError(
+                s.into(),
+                format!(
+                    "expected {} base16, {} base32 or {} base64 characters for {}",
+                    len * 2,
+                    nix::encoded_len(len),
+                    (len + 2) / 3 * 4,
+                    algorithm
+                ),
+            ));
+        };
+        Ok(Integrity { hashes: vec![hash] })
+    }
+
+    /// Parses an [RFC 9530](https://www.rfc-editor.org/rfc/rfc9530)
+    /// `Content-Digest` or `Repr-Digest` header value, like
+    /// `sha-256=:<base64>:`.
+    ///
+    /// `sha-256`, `sha-512` and the deprecated `sha` map to their
+    /// `Algorithm`s. Other algorithms are kept as
+    /// [`Algorithm::Unknown`](enum.Algorithm.html#variant.Unknown), so they
+    /// can be passed on but not checked.
+    ///
+    /// # Example
+    /// ```
+    /// use ssri::{Algorithm, Integrity};
+    ///
+    /// let sri = Integrity::from_content_digest(
+    ///     "sha-256=:X48E9qOokqqrvdts8nOJRJN3OWDUoyWxBf7kbu9DBPE=:",
+    /// ).unwrap();
+    /// assert_eq!(sri.check(br#"{"hello": "world"}"#).unwrap(), Algorithm::Sha256);
+    /// ```
+    pub fn from_content_digest(value: &str) -> Result<Integrity, Error> {
+        let mut hashes = http::parse_digest_fields(value)?;
+        if hashes.is_empty() {
+            return Err(Error::ParseIntegrityError(value.into()));
+        }
+        hashes.sort();
+        Ok(Integrity { hashes })
+    }
+
+    /// Formats this `Integrity` as an [RFC 9530](https://www.rfc-editor.org/rfc/rfc9530)
+    /// `Content-Digest` or `Repr-Digest` header value. Hashes whose algorithm
+    /// has no HTTP name, like `sha384` or `xxh3`, are left out, and `None` is
+    /// returned if that leaves nothing.
+    ///
+    /// # Example
+    /// ```
+    /// use ssri::Integrity;
+    ///
+    /// let sri = Integrity::from(br#"{"hello": "world"}"#);
+    /// assert_eq!(
+    ///     sri.to_content_digest().unwrap(),
+    ///     "sha-256=:X48E9qOokqqrvdts8nOJRJN3OWDUoyWxBf7kbu9DBPE=:"
+    /// );
+    /// ```
+    pub fn to_content_digest(&self) -> Option<String> {
+        http::format_digest_fields(&self.hashes)
+    }
+
+    /// Parses a legacy [RFC 3230](https://www.rfc-editor.org/rfc/rfc3230)
+    /// `Digest` header value, like `SHA-256=<base64>, MD5=<base64>`.
+    /// Algorithm names are case-insensitive.
+    ///
+    /// Digests for algorithms `ssri` doesn't support are kept verbatim as
+    /// [`Algorithm::Unknown`](enum.Algorithm.html#variant.Unknown) hashes, even
+    /// when they aren't base64, so they survive
+    /// [`to_digest_header`](#method.to_digest_header). Ones containing `-`,
+    /// `?` or whitespace are dropped, since they couldn't be parsed back out
+    /// of the integrity string.
+    ///
+    /// # Example
+    /// ```
+    /// use ssri::{Algorithm, Integrity};
+    ///
+    /// let sri = Integrity::from_digest_header(
+    ///     "SHA-256=uU0nuZNNPgilLlLX2n2r+sSE7+N6U4DukIj3rOLvzek=, UNIXsum=30637",
+    /// ).unwrap();
+    /// assert_eq!(sri.check(b"hello world").unwrap(), Algorithm::Sha256);
+    /// ```
+    pub fn from_digest_header(value: &str) -> Result<Integrity, Error> {
+        let mut hashes = http::parse_legacy_digest(value)?;
+        if hashes.is_empty() {
+            return Err(Error::ParseIntegrityError(value.into()));
+        }
+        hashes.sort();
+        Ok(Integrity { hashes })
+    }
+
+    /// Formats this `Integrity` as a legacy [RFC 3230](https://www.rfc-editor.org/rfc/rfc3230)
+    /// `Digest` header value. As with
+    /// [`to_content_digest`](#method.to_content_digest), hashes whose
+    /// algorithm has no HTTP name are left out.
+    ///
+    /// # Example
+    /// ```
+    /// use ssri::Integrity;
+    ///
+    /// let sri = Integrity::from(b"hello world");
+    /// assert_eq!(
+    ///     sri.to_digest_header().unwrap(),
+    ///     "SHA-256=uU0nuZNNPgilLlLX2n2r+sSE7+N6U4DukIj3rOLvzek="
+    /// );
+    /// ```
+    pub fn to_digest_header(&self) -> Option<String> {
+        http::format_legacy_digest(&self.hashes)
+    }
+
+    /// Parses a `Content-MD5` header value. MD5 isn't built in, so the hash
+    /// is kept as an opaque `md5` [`Algorithm::Unknown`](enum.Algorithm.html#variant.Unknown)
+    /// unless an `md5` algorithm has been added with
+    /// [`register_algorithm`](fn.register_algorithm.html).
+    ///
+    /// # Example
+    /// ```
+    /// use ssri::Integrity;
+    ///
+    /// let sri = Integrity::from_content_md5("XrY7u+Ae7tCTyyK7j1rNww==").unwrap();
+    /// assert_eq!(sri.to_string(), "md5-XrY7u+Ae7tCTyyK7j1rNww==");
+    /// ```
+    pub fn from_content_md5(value: &str) -> Result<Integrity, Error> {
+        Ok(Integrity {
+            hashes: vec![http::parse_content_md5(value)?],
+        })
+    }
+
+    /// The `Content-MD5` header value for this `Integrity`, if it has an
+    /// `md5` hash.
+    pub fn to_content_md5(&self) -> Option<String> {
+        http::format_content_md5(&self.hashes)
+    }
+
     /// Join together two `Integrity` instances. Hashes will be grouped and
     /// sorted by algorithm but otherwise kept in the same order.
     ///
@@ -191,9 +584,80 @@ impl Integrity {
         checker.result()
     }
 
+    /// Generates a sha256 `Integrity` from the contents of the file at
+    /// `path`. Use [`IntegrityOpts::read_path`] to pick the algorithms.
+    ///
+    /// With the `mmap` feature, large files are memory-mapped. Only use it on
+    /// files no other process will truncate while they're being read: if that
+    /// happens, reading the mapping raises `SIGBUS`, which kills the process.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use ssri::Integrity;
+    ///
+    /// let sri = Integrity::from_path("package.tgz")?;
+    /// # Ok::<(), ssri::Error>(())
+    /// ```
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Integrity, Error> {
+        Ok(IntegrityOpts::new()
+            .algorithm(Algorithm::Sha256)
+            .read_path(path)?
+            .result())
+    }
+
+    /// Checks the contents of the file at `path` against this `Integrity`,
+    /// like [`Integrity::check`].
+    ///
+    /// With the `mmap` feature, large files are memory-mapped. Only use it on
+    /// files no other process will truncate while they're being read: if that
+    /// happens, reading the mapping raises `SIGBUS`, which kills the process.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use ssri::Integrity;
+    ///
+    /// let sri: Integrity = "sha256-uU0nuZNNPgilLlLX2n2r+sSE7+N6U4DukIj3rOLvzek=".parse()?;
+    /// let algorithm = sri.check_path("hello.txt")?;
+    /// # Ok::<(), ssri::Error>(())
+    /// ```
+    pub fn check_path<P: AsRef<Path>>(&self, path: P) -> Result<Algorithm, Error> {
+        let mut checker = IntegrityChecker::new(self.clone());
+        file::read_path(path.as_ref(), |chunk| checker.input(chunk))?;
+        checker.result()
+    }
+
+    /// Like [`Integrity::check`], but only trusts the algorithms `policy`
+    /// allows. Fails with [`Error::AlgorithmNotAllowed`] if none of this
+    /// `Integrity`'s hashes use an allowed algorithm.
+    ///
+    /// # Example
+    /// ```
+    /// use ssri::{Algorithm, Integrity, VerificationPolicy};
+    ///
+    /// let data = b"hello";
+    /// let sri = Integrity::from(data);
+    /// let policy = VerificationPolicy::new().non_cryptographic(false);
+    /// assert_eq!(sri.check_with_policy(data, &policy).unwrap(), Algorithm::Sha256);
+    /// ```
+    pub fn check_with_policy<B: AsRef<[u8]>>(
+        &self,
+        data: B,
+        policy: &VerificationPolicy,
+    ) -> Result<Algorithm, Error> {
+        IntegrityChecker::new(self.clone())
+            .verification_policy(policy)
+            .chain(data)
+            .result()
+    }
+
     /// Converts the first `Hash` in this `Integrity` into its hex string
     /// format.
     ///
+    /// # Panics
+    ///
+    /// Panics if this `Integrity` is empty or the digest isn't valid base64.
+    /// Use [`Integrity::try_to_hex`] to get an error instead.
+    ///
     /// # Example
     /// ```
     /// use ssri::{Algorithm, Integrity};
@@ -204,19 +668,36 @@ impl Integrity {
     /// assert_eq!(hex, "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824".to_owned());
     /// ```
     pub fn to_hex(&self) -> (Algorithm, String) {
-        let hash = self.hashes.get(0).unwrap();
-        (
-            hash.algorithm,
-            hex::encode(
-                base64::prelude::BASE64_STANDARD
-                    .decode(&hash.digest)
-                    .unwrap(),
-            ),
-        )
+        self.try_to_hex().unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Same as [`Integrity::to_hex`], but fails with
+    /// [`Error::InvalidDigestError`] instead of panicking if this `Integrity`
+    /// is empty or the digest isn't valid base64.
+    ///
+    /// # Example
+    /// ```
+    /// use ssri::{Algorithm, Error, Integrity};
+    ///
+    /// let sri = Integrity::from(b"hello");
+    /// assert_eq!(sri.try_to_hex().unwrap().0, Algorithm::Sha256);
+    ///
+    /// let sri: Integrity = "sha256-deadbeef==".parse().unwrap();
+    /// assert!(matches!(sri.try_to_hex(), Err(Error::InvalidDigestError(..))));
+    /// ```
+    pub fn try_to_hex(&self) -> Result<(Algorithm, String), Error> {
+        let hash = self.hashes.first().ok_or_else(|| {
+            Error::InvalidDigestError(String::new(), String::from("there are no hashes"))
+        })?;
+        let bytes = hash.as_bytes().ok_or_else(|| {
+            Error::InvalidDigestError(hash.to_string(), String::from("invalid base64"))
+        })?;
+        Ok((hash.algorithm, hex::encode(bytes)))
     }
 
     /// Compares `self` against a given SRI to see if there's a match. The
-    /// deciding algorithm is determined by `other`.
+    /// deciding algorithm is determined by `other`. Option expressions on
+    /// either side are ignored.
     ///
     /// # Example
     /// ```
@@ -237,7 +718,7 @@ impl Integrity {
                     .hashes
                     .iter()
                     .filter(|i| i.algorithm == algo)
-                    .any(|i| h == i)
+                    .any(|i| h.ct_eq(i))
             })
             .map(|h| h.algorithm)
     }
@@ -251,26 +732,200 @@ mod tests {
     fn parse() {
         let sri: Integrity = "sha1-deadbeef=".parse().unwrap();
         assert_eq!(
-            sri.hashes.get(0).unwrap(),
-            &Hash {
-                algorithm: Algorithm::Sha1,
-                digest: String::from("deadbeef=")
-            }
+            sri.hashes.first().unwrap(),
+            &Hash::from_base64(Algorithm::Sha1, "deadbeef=")
+        );
+        let sri: Integrity = "sha1-3q2+7w==".parse().unwrap();
+        assert_eq!(
+            sri.hashes.first().unwrap(),
+            &Hash::from_bytes(Algorithm::Sha1, &[0xde, 0xad, 0xbe, 0xef])
         )
     }
 
+    #[test]
+    fn parse_strict() {
+        use crate::Error;
+
+        let sri = Integrity::from(b"hello world").concat(
+            IntegrityOpts::new()
+                .algorithm(Algorithm::Sha512)
+                .chain(b"hello world")
+                .result(),
+        );
+        assert_eq!(Integrity::parse_strict(&sri.to_string()).unwrap(), sri);
+
+        let bad = format!("{} sha512-deadbeef", sri);
+        assert_eq!(
+            Integrity::parse_strict(&bad),
+            Err(Error::InvalidDigestError(
+                "sha512-deadbeef".into(),
+                "expected 64 bytes for sha512, got 6".into()
+            ))
+        );
+    }
+
+    #[test]
+    fn parse_lenient() {
+        use super::UnknownTokens;
+
+        let source = "sha3-999-aaaa md5-bbbb?opt sha1-cccc - sha256-dddd";
+        let report = Integrity::parse_lenient(source, UnknownTokens::Drop);
+        assert_eq!(report.integrity.to_string(), "sha256-dddd sha1-cccc");
+        assert_eq!(report.skipped, vec!["sha3-999-aaaa", "md5-bbbb?opt", "-"]);
+
+        let report = Integrity::parse_lenient(source, UnknownTokens::Keep);
+        assert_eq!(
+            report.integrity.to_string(),
+            "sha256-dddd sha1-cccc md5-bbbb?opt sha3-999-aaaa"
+        );
+        assert_eq!(report.skipped, vec!["-"]);
+        assert!(source.parse::<Integrity>().is_err());
+
+        let kept = report.integrity.to_string();
+        assert_eq!(kept.parse::<Integrity>().unwrap(), report.integrity);
+        assert!(Integrity::parse_strict(&kept).is_err());
+    }
+
+    #[test]
+    fn unknown_roundtrip_json() {
+        let source = "sha1-Kq5sNclPz7QV2+lfQIuc6R7oRu0= md5-XrY7u+Ae7tCTyyK7j1rNww== unixsum-30637";
+        let sri: Integrity = source.parse().unwrap();
+        assert_eq!(sri.to_string(), source);
+        let json = serde_json::to_string(&sri).unwrap();
+        let de: Integrity = serde_json::from_str(&json).unwrap();
+        assert_eq!(de, sri);
+        assert_eq!(de.to_string(), source);
+    }
+
     #[test]
     fn from_hex() {
+        use crate::Error;
+
         let expected_integrity = Integrity::from(b"hello world");
         let hex = String::from("b94d27b9934d3e08a52e52d7da7dabfac484efe37a5380ee9088f7ace2efcde9");
         assert_eq!(
             Integrity::from_hex(hex, Algorithm::Sha256).unwrap(),
             expected_integrity
         );
+        assert!(matches!(
+            Integrity::from_hex("abcd", Algorithm::Sha256),
+            Err(Error::InvalidDigestError(..))
+        ));
+        assert!(matches!(
+            Integrity::from_hex("abc", Algorithm::Sha256),
+            Err(Error::HexDecodeError(..))
+        ));
+    }
+
+    #[test]
+    fn from_oci_digest() {
+        use crate::Error;
+
+        let hex = "b94d27b9934d3e08a52e52d7da7dabfac484efe37a5380ee9088f7ace2efcde9";
+        let sri = Integrity::from_oci_digest(&format!("sha256:{}", hex)).unwrap();
+        assert_eq!(sri, Integrity::from(b"hello world"));
+        assert_eq!(
+            sri.hashes[0].to_oci_digest().unwrap(),
+            format!("sha256:{}", hex)
+        );
+
+        let sri = IntegrityOpts::new()
+            .algorithm(Algorithm::Sha512)
+            .chain(b"hello world")
+            .result();
+        let digest = sri.hashes[0].to_oci_digest().unwrap();
+        assert!(digest.starts_with("sha512:"));
+        assert_eq!(Integrity::from_oci_digest(&digest).unwrap(), sri);
+
+        let unknown = Integrity::from_oci_digest("multihash_sha256:1220abcd").unwrap();
+        assert_eq!(unknown.to_string(), "multihash_sha256-1220abcd");
+        assert_eq!(
+            unknown.hashes[0].to_oci_digest().unwrap(),
+            "multihash_sha256:1220abcd"
+        );
+        assert_eq!(unknown.to_string().parse::<Integrity>().unwrap(), unknown);
+        assert!(matches!(
+            unknown.pick_algorithm(),
+            Algorithm::Unknown(name) if name.as_str() == "multihash_sha256"
+        ));
+
+        for bad in &[
+            "sha256",
+            "SHA256:abcd",
+            "sha256+:abcd",
+            ":abcd",
+            "sha256:",
+            "sha256:abcd",
+            "sha256:B94D27B9934D3E08A52E52D7DA7DABFAC484EFE37A5380EE9088F7ACE2EFCDE9",
+        ] {
+            assert!(Integrity::from_oci_digest(bad).is_err(), "{}", bad);
+        }
+        assert!(matches!(
+            Integrity::from_oci_digest("sha256:abc"),
+            Err(Error::InvalidDigestError(..))
+        ));
+        for verbatim in &["foo:ABC=", "foo:abc", "foo:a_b"] {
+            let foo = Integrity::from_oci_digest(verbatim).unwrap();
+            assert_eq!(foo.hashes[0].to_oci_digest().unwrap(), *verbatim);
+            assert_eq!(foo.to_string().parse::<Integrity>().unwrap(), foo);
+        }
+        assert!(matches!(
+            Integrity::from_oci_digest("foo:a+b"),
+            Err(Error::InvalidDigestError(..))
+        ));
+        assert!(matches!(
+            Integrity::from_oci_digest("foo:a-b"),
+            Err(Error::InvalidDigestError(..))
+        ));
+    }
+
+    #[test]
+    fn nix_hash() {
+        use crate::Error;
+
+        // `nix hash convert` of sha256("abc").
+        let sri: Integrity = "sha256-ungWv48Bz+pBQUDeXa4iI7ADYaOWF3qctBD/YfIAFa0="
+            .parse()
+            .unwrap();
+        let base16 = "sha256:ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad";
+        let base32 = "sha256:1b8m03r63zqhnjf7l5wnldhh7c134ap5vpj0850ymkq1iyzicy5s";
+        assert_eq!(sri.hashes[0].to_nix_base16().unwrap(), base16);
+        assert_eq!(sri.hashes[0].to_nix_base32().unwrap(), base32);
+        assert_eq!(sri, Integrity::from(b"abc"));
+        for hash in &[
+            base16,
+            base32,
+            "sha256:ungWv48Bz+pBQUDeXa4iI7ADYaOWF3qctBD/YfIAFa0=",
+            "sha256-ungWv48Bz+pBQUDeXa4iI7ADYaOWF3qctBD/YfIAFa0=",
+        ] {
+            assert_eq!(Integrity::from_nix_hash(hash).unwrap(), sri, "{}", hash);
+        }
+
+        let sri = IntegrityOpts::new()
+            .algorithm(Algorithm::Sha512)
+            .chain(b"abc")
+            .result();
+        let base32 = sri.hashes[0].to_nix_base32().unwrap();
+        assert_eq!(Integrity::from_nix_hash(&base32).unwrap(), sri);
+
+        assert!(matches!(
+            Integrity::from_nix_hash("sha256:abcd"),
+            Err(Error::InvalidDigestError(..))
+        ));
+        assert!(matches!(
+            Integrity::from_nix_hash("md5:abcd"),
+            Err(Error::ParseIntegrityError(..))
+        ));
+        assert!(matches!(
+            Integrity::from_nix_base32("vw46m23bizj4n8afrc0fj19wrp7mj3c0", Algorithm::Sha256),
+            Err(Error::InvalidDigestError(..))
+        ));
     }
 
     #[test]
     fn to_hex() {
+        use crate::Error;
+
         let sri = Integrity::from(b"hello world");
         assert_eq!(
             sri.to_hex(),
@@ -278,7 +933,16 @@ mod tests {
                 Algorithm::Sha256,
                 String::from("b94d27b9934d3e08a52e52d7da7dabfac484efe37a5380ee9088f7ace2efcde9")
             )
-        )
+        );
+        let raw: Integrity = "sha256-deadbeef==".parse().unwrap();
+        assert!(matches!(
+            raw.try_to_hex(),
+            Err(Error::InvalidDigestError(..))
+        ));
+        assert!(matches!(
+            Integrity { hashes: vec![] }.try_to_hex(),
+            Err(Error::InvalidDigestError(..))
+        ));
     }
 
     #[test]
@@ -295,6 +959,28 @@ mod tests {
         assert_eq!(sri2.matches(&sri1), None)
     }
 
+    #[test]
+    fn matches_ignores_options() {
+        let sri = Integrity::from(b"hello world");
+        let with_options: Integrity = format!("{}?foo?bar", sri).parse().unwrap();
+        assert_eq!(with_options.hashes[0].options(), ["foo", "bar"]);
+        assert_eq!(sri.matches(&with_options), Some(Algorithm::Sha256));
+        assert_eq!(with_options.matches(&sri), Some(Algorithm::Sha256));
+        assert_eq!(with_options.check(b"hello world"), Ok(Algorithm::Sha256));
+    }
+
+    #[test]
+    fn options_roundtrip_json() {
+        let source =
+            "sha256-uU0nuZNNPgilLlLX2n2r+sSE7+N6U4DukIj3rOLvzek=?ct=application/javascript";
+        let sri: Integrity = source.parse().unwrap();
+        let json = serde_json::to_string(&sri).unwrap();
+        assert_eq!(json, format!("\"{}\"", source));
+        let de: Integrity = serde_json::from_str(&json).unwrap();
+        assert_eq!(de, sri);
+        assert_eq!(de.to_string(), source);
+    }
+
     #[test]
     fn de_json() {
         use serde_derive::Deserialize;
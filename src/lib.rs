@@ -33,19 +33,80 @@ assert_eq!(sri.check(b"hello world").unwrap(), Algorithm::Sha256);
 
 You can also use [`IntegrityOpts`](struct.IntegrityOpts.html) and [`IntegrityChecker`](struct.IntegrityChecker.html) to generate
 and check subresource integrity, respectively. These allow things like multiple algorithms, and
-incremental/streamed data input.
+incremental/streamed data input. [`IntegrityReader`](struct.IntegrityReader.html)
+wraps any [`Read`](std::io::Read) to check data as it streams through, and
+[`IntegrityWriter`](struct.IntegrityWriter.html) does the same for a
+[`Write`](std::io::Write). With the `tokio` or `futures` features enabled,
+[`AsyncIntegrityReader`](struct.AsyncIntegrityReader.html) and
+[`AsyncIntegrityWriter`](struct.AsyncIntegrityWriter.html) do the same for
+async readers and writers, and `futures` also adds
+[`IntegrityStream`](struct.IntegrityStream.html) for streams of byte chunks.
+
+Files can be hashed and checked by path with
+[`Integrity::from_path`](struct.Integrity.html#method.from_path),
+[`IntegrityOpts::read_path`](struct.IntegrityOpts.html#method.read_path) and
+[`Integrity::check_path`](struct.Integrity.html#method.check_path). Enable the
+`mmap` feature to memory-map large files instead of reading them. Mapped
+files must not be truncated while they're hashed, or the process is killed
+with `SIGBUS`.
+[`BatchVerifier`](struct.BatchVerifier.html) checks many files or readers at
+once on a pool of worker threads, and [`DirIntegrity`](struct.DirIntegrity.html)
+generates a single `Integrity` for a whole directory tree.
+
+With the `rayon` feature, [`IntegrityOpts::parallel`](struct.IntegrityOpts.html#method.parallel)
+updates several algorithms' hashers at once on rayon's thread pool.
+
+For long-running or resumable transfers,
+[`IntegrityOpts::export_state`](struct.IntegrityOpts.html#method.export_state)
+checkpoints the SHA-2 and `xxh3` hashers so they can be picked up later with
+[`IntegrityOpts::restore_state`](struct.IntegrityOpts.html#method.restore_state).
+This needs the slower hashers enabled by
+[`IntegrityOpts::resumable`](struct.IntegrityOpts.html#method.resumable).
+
+Algorithms beyond the built-in ones can be added at runtime by implementing
+[`HashAlgorithm`](trait.HashAlgorithm.html) and passing it to
+[`register_algorithm`](fn.register_algorithm.html).
 */
 
 mod algorithm;
+#[cfg(any(feature = "tokio", feature = "futures"))]
+mod async_io;
+mod batch;
 mod checker;
+mod checksums;
+mod dir;
 mod errors;
+mod file;
 mod hash;
+mod http;
 mod integrity;
+mod nix;
 mod opts;
+mod policy;
+mod reader;
+mod registry;
+mod resumable;
+mod stream;
+mod writer;
 
-pub use algorithm::Algorithm::{self, *};
-pub use checker::IntegrityChecker;
+// Newer variants are only reachable through `Algorithm`, to keep the crate
+// root from growing with every algorithm.
+pub use algorithm::Algorithm::{self, Sha1, Sha256, Sha384, Sha512, Xxh3};
+pub use algorithm::UnknownAlgorithm;
+#[cfg(feature = "futures")]
+pub use async_io::IntegrityStream;
+#[cfg(any(feature = "tokio", feature = "futures"))]
+pub use async_io::{AsyncIntegrityReader, AsyncIntegrityWriter};
+pub use batch::{BatchReport, BatchSource, BatchVerifier};
+pub use checker::{CheckReport, IntegrityChecker, MatchPolicy};
+pub use checksums::{ChecksumFormat, ChecksumList, ChecksumReport};
+pub use dir::{DirDiff, DirEntry, DirIntegrity, DirManifest};
 pub use errors::Error;
 pub use hash::Hash;
-pub use integrity::Integrity;
+pub use http::DigestPreferences;
+pub use integrity::{Integrity, ParseReport, UnknownTokens};
 pub use opts::IntegrityOpts;
+pub use policy::VerificationPolicy;
+pub use reader::IntegrityReader;
+pub use registry::{register_algorithm, AlgorithmHasher, CustomAlgorithm, HashAlgorithm};
+pub use writer::IntegrityWriter;
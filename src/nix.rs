@@ -0,0 +1,84 @@
+/// Nix's base32 alphabet. It omits `e`, `o`, `t` and `u`.
+const ALPHABET: &[u8; 32] = b"0123456789abcdfghijklmnpqrsvwxyz";
+
+/// Number of base32 characters Nix uses for a digest of `len` bytes.
+pub(crate) fn encoded_len(len: usize) -> usize {
+    if len == 0 {
+        0
+    } else {
+        (len * 8 - 1) / 5 + 1
+    }
+}
+
+/// Encodes `bytes` the way Nix does. Unlike RFC 4648 base32, the bits are
+/// read from the end of the digest first, and there's no padding.
+pub(crate) fn encode(bytes: &[u8]) -> String {
+    (0..encoded_len(bytes.len()))
+        .rev()
+        .map(|n| {
+            let (i, j) = (n * 5 / 8, n * 5 % 8);
+            let low = usize::from(bytes[i] >> j);
+            let high = bytes.get(i + 1).map_or(0, |b| usize::from(*b) << (8 - j));
+            ALPHABET[(low | high) & 0x1f] as char
+        })
+        .collect()
+}
+
+/// Decodes a Nix base32 string into a digest of exactly `len` bytes.
+pub(crate) fn decode(s: &str, len: usize) -> Result<Vec<u8>, String> {
+    if s.len() != encoded_len(len) {
+        return Err(format!(
+            "expected {} base32 characters, got {}",
+            encoded_len(len),
+            s.len()
+        ));
+    }
+    let mut bytes = vec![0; len];
+    for (n, c) in s.chars().rev().enumerate() {
+        let digit = ALPHABET
+            .iter()
+            .position(|a| *a as char == c)
+            .ok_or_else(|| format!("invalid base32 character `{}`", c))? as u16;
+        let (i, j) = (n * 5 / 8, n * 5 % 8);
+        bytes[i] |= (digit << j) as u8;
+        let carry = digit >> (8 - j);
+        if i + 1 < len {
+            bytes[i + 1] |= carry as u8;
+        } else if carry != 0 {
+            return Err(String::from("base32 digest is too large"));
+        }
+    }
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode, encode};
+
+    // From the `nix hash convert` manual page.
+    const SHA1_HEX: &str = "800d59cfcd3c05e900cb4e214be48f6b886a08df";
+    const SHA1_NIX32: &str = "vw46m23bizj4n8afrc0fj19wrp7mj3c0";
+
+    #[test]
+    fn roundtrip() {
+        let bytes = hex::decode(SHA1_HEX).unwrap();
+        assert_eq!(encode(&bytes), SHA1_NIX32);
+        assert_eq!(decode(SHA1_NIX32, 20).unwrap(), bytes);
+
+        for len in 1..70 {
+            let bytes = (0..len).map(|i| (i * 37 + 11) as u8).collect::<Vec<_>>();
+            assert_eq!(decode(&encode(&bytes), len).unwrap(), bytes);
+        }
+    }
+
+    #[test]
+    fn invalid() {
+        assert!(decode(&SHA1_NIX32[1..], 20).is_err());
+        assert!(decode(&SHA1_NIX32.replace('v', "e"), 20).is_err());
+        // 52 characters hold 260 bits, so the first one can only carry the
+        // top 4 bits of a sha256 digest.
+        assert!(decode(&"0".repeat(52), 32).is_ok());
+        assert!(decode(&format!("1{}", "0".repeat(51)), 32).is_ok());
+        assert!(decode(&format!("z{}", "0".repeat(51)), 32).is_err());
+    }
+}
@@ -1,33 +1,38 @@
 use std::fmt::Debug;
+use std::path::Path;
+
+use base64::prelude::BASE64_STANDARD;
+use base64::Engine as _;
 
 use crate::algorithm::Algorithm;
+use crate::errors::Error;
+use crate::file;
 use crate::hash::Hash;
 use crate::integrity::Integrity;
+use crate::registry::AlgorithmHasher;
 
-use base64::prelude::BASE64_STANDARD;
-use base64::Engine;
-use digest::Digest;
-
-#[allow(clippy::enum_variant_names)]
-#[non_exhaustive]
-#[derive(Clone)]
-enum Hasher {
-    Sha1(sha1::Sha1),
-    Sha256(sha2::Sha256),
-    Sha384(sha2::Sha384),
-    Sha512(sha2::Sha512),
-    Xxh3(Box<xxhash_rust::xxh3::Xxh3>),
+/// Inputs smaller than this are hashed on the calling thread even in parallel
+/// mode, since handing them off costs more than hashing them.
+#[cfg(feature = "rayon")]
+const PARALLEL_THRESHOLD: usize = 16 * 1024;
+
+struct Hasher {
+    algorithm: Algorithm,
+    inner: Box<dyn AlgorithmHasher>,
+}
+
+impl Clone for Hasher {
+    fn clone(&self) -> Self {
+        Hasher {
+            algorithm: self.algorithm,
+            inner: self.inner.box_clone(),
+        }
+    }
 }
 
 impl Debug for Hasher {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Self::Sha1(arg0) => f.debug_tuple("Sha1").field(arg0).finish(),
-            Self::Sha256(arg0) => f.debug_tuple("Sha256").field(arg0).finish(),
-            Self::Sha384(arg0) => f.debug_tuple("Sha384").field(arg0).finish(),
-            Self::Sha512(arg0) => f.debug_tuple("Sha512").field(arg0).finish(),
-            Self::Xxh3(_arg0) => f.debug_tuple("Xxh3").finish(),
-        }
+        f.debug_tuple("Hasher").field(&self.algorithm).finish()
     }
 }
 
@@ -51,6 +56,9 @@ let sri = IntegrityOpts::new()
 pub struct IntegrityOpts {
     hashers: Vec<Hasher>,
     disturbed: bool,
+    resumable: bool,
+    #[cfg(feature = "rayon")]
+    parallel: bool,
 }
 
 impl IntegrityOpts {
@@ -59,37 +67,113 @@ impl IntegrityOpts {
         IntegrityOpts {
             hashers: vec![],
             disturbed: false,
+            resumable: false,
+            #[cfg(feature = "rayon")]
+            parallel: false,
+        }
+    }
+
+    /// Creates a new hasher for `algo`, which must have an implementation.
+    fn hasher(&self, algo: Algorithm) -> Box<dyn AlgorithmHasher> {
+        let implementation = algo
+            .implementation()
+            .unwrap_or_else(|| panic!("Can't generate hashes for unknown algorithm {}", algo));
+        if self.resumable {
+            implementation.resumable_hasher()
+        } else {
+            implementation.hasher()
         }
     }
 
     /// Generate a hash for this algorithm. Can be called multiple times to generate an `Integrity` string with multiple entries.
+    ///
+    /// Panics if `algo` is an [`Algorithm::Unknown`].
     pub fn algorithm(mut self, algo: Algorithm) -> Self {
         if self.disturbed {
             panic!("Can't add new algorithms if IntegrityOpts::input() has already been called");
         }
-        self.hashers.push(match algo {
-            Algorithm::Sha1 => Hasher::Sha1(sha1::Sha1::new()),
-            Algorithm::Sha256 => Hasher::Sha256(sha2::Sha256::new()),
-            Algorithm::Sha384 => Hasher::Sha384(sha2::Sha384::new()),
-            Algorithm::Sha512 => Hasher::Sha512(sha2::Sha512::new()),
-            Algorithm::Xxh3 => Hasher::Xxh3(Box::new(xxhash_rust::xxh3::Xxh3::new())),
+        let inner = self.hasher(algo);
+        self.hashers.push(Hasher {
+            algorithm: algo,
+            inner,
         });
         self
     }
 
+    /// Use hashers whose running state can be checkpointed with
+    /// [`IntegrityOpts::export_state`]. For the SHA-2 algorithms and `xxh3`,
+    /// these are slower than the default ones, so only turn this on when
+    /// you need it.
+    ///
+    /// Panics if `input()` has already been called.
+    pub fn resumable(mut self, resumable: bool) -> Self {
+        if self.disturbed {
+            panic!("Can't switch hashers if IntegrityOpts::input() has already been called");
+        }
+        self.resumable = resumable;
+        let algorithms = self.hashers.iter().map(|h| h.algorithm).collect::<Vec<_>>();
+        self.hashers = algorithms
+            .into_iter()
+            .map(|algorithm| Hasher {
+                algorithm,
+                inner: self.hasher(algorithm),
+            })
+            .collect();
+        self
+    }
+
+    /// Update the hashers on rayon's thread pool instead of one after another
+    /// on the calling thread. This only kicks in when more than one algorithm
+    /// is configured and for inputs large enough to be worth it, and produces
+    /// the same `Integrity` either way.
+    ///
+    /// # Example
+    /// ```
+    /// # use ssri::{Algorithm, IntegrityOpts};
+    /// let data = vec![0u8; 1024 * 1024];
+    /// let sri = IntegrityOpts::new()
+    ///     .algorithm(Algorithm::Sha512)
+    ///     .algorithm(Algorithm::Sha256)
+    ///     .algorithm(Algorithm::Sha1)
+    ///     .parallel(true)
+    ///     .chain(&data)
+    ///     .result();
+    /// assert_eq!(sri.hashes.len(), 3);
+    /// ```
+    #[cfg(feature = "rayon")]
+    pub fn parallel(mut self, parallel: bool) -> Self {
+        self.parallel = parallel;
+        self
+    }
+
+    /// Whether `input()` has been called since this IntegrityOpts was created
+    /// or reset.
+    pub(crate) fn is_disturbed(&self) -> bool {
+        self.disturbed
+    }
+
+    /// Whether this IntegrityOpts will generate a hash for `algo`.
+    pub(crate) fn has_algorithm(&self, algo: Algorithm) -> bool {
+        self.hashers.iter().any(|h| h.algorithm == algo)
+    }
+
     /// Add some data to this IntegrityOpts. All internal hashers will be updated for all configured `Algorithm`s.
     pub fn input<B: AsRef<[u8]>>(&mut self, input: B) {
         let input = input.as_ref();
         self.disturbed = true;
-        for hasher in self.hashers.iter_mut() {
-            match hasher {
-                Hasher::Sha1(h) => digest::Digest::update(h, input),
-                Hasher::Sha256(h) => digest::Digest::update(h, input),
-                Hasher::Sha384(h) => digest::Digest::update(h, input),
-                Hasher::Sha512(h) => digest::Digest::update(h, input),
-                Hasher::Xxh3(h) => h.update(input),
+        #[cfg(feature = "rayon")]
+        {
+            if self.parallel && self.hashers.len() > 1 && input.len() >= PARALLEL_THRESHOLD {
+                use rayon::prelude::*;
+                self.hashers
+                    .par_iter_mut()
+                    .for_each(|hasher| hasher.inner.update(input));
+                return;
             }
         }
+        for hasher in self.hashers.iter_mut() {
+            hasher.inner.update(input);
+        }
     }
 
     /// Same as `IntegrityOpts::input`, but allows chaining.
@@ -98,6 +182,100 @@ impl IntegrityOpts {
         self
     }
 
+    /// Adds the contents of the file at `path` to this IntegrityOpts. With the
+    /// `mmap` feature, large files are memory-mapped instead of read. Only use
+    /// it on files no other process will truncate while they're being read:
+    /// if that happens, reading the mapping raises `SIGBUS`, which kills the
+    /// process.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use ssri::{Algorithm, IntegrityOpts};
+    /// let sri = IntegrityOpts::new()
+    ///     .algorithm(Algorithm::Sha512)
+    ///     .read_path("package.tgz")?
+    ///     .result();
+    /// # Ok::<(), ssri::Error>(())
+    /// ```
+    pub fn read_path<P: AsRef<Path>>(mut self, path: P) -> Result<Self, Error> {
+        file::read_path(path.as_ref(), |chunk| self.input(chunk))?;
+        Ok(self)
+    }
+
+    /// Serializes the running state of every hasher, so hashing can be
+    /// picked up later with [`IntegrityOpts::restore_state`], even in another
+    /// process. The state is a string of space-separated `algorithm-base64`
+    /// entries, and should otherwise be treated as opaque.
+    ///
+    /// The SHA-2 algorithms and `xxh3` support this once
+    /// [`IntegrityOpts::resumable`] is turned on, as do user-defined
+    /// algorithms that implement
+    /// [`AlgorithmHasher::export_state`](trait.AlgorithmHasher.html#method.export_state).
+    /// For anything else, this fails with [`Error::HasherStateError`].
+    ///
+    /// # Example
+    /// ```
+    /// # use ssri::{Algorithm, IntegrityOpts};
+    /// let opts = IntegrityOpts::new()
+    ///     .algorithm(Algorithm::Sha512)
+    ///     .algorithm(Algorithm::Sha256)
+    ///     .resumable(true)
+    ///     .chain(b"hello ");
+    /// let state = opts.export_state().unwrap();
+    /// // ...later...
+    /// let sri = IntegrityOpts::restore_state(&state).unwrap().chain(b"world").result();
+    /// assert_eq!(
+    ///     sri,
+    ///     IntegrityOpts::new()
+    ///         .algorithm(Algorithm::Sha512)
+    ///         .algorithm(Algorithm::Sha256)
+    ///         .chain(b"hello world")
+    ///         .result()
+    /// );
+    /// ```
+    pub fn export_state(&self) -> Result<String, Error> {
+        let entries = self
+            .hashers
+            .iter()
+            .map(|h| {
+                let state = h.inner.export_state().ok_or_else(|| {
+                    Error::HasherStateError(if self.resumable {
+                        format!("{} hashers can't export their state", h.algorithm)
+                    } else {
+                        format!(
+                            "{} hashers can't export their state unless IntegrityOpts::resumable is set",
+                            h.algorithm
+                        )
+                    })
+                })?;
+                Ok(format!("{}-{}", h.algorithm, BASE64_STANDARD.encode(state)))
+            })
+            .collect::<Result<Vec<String>, Error>>()?;
+        Ok(entries.join(" "))
+    }
+
+    /// Recreates an IntegrityOpts from a state returned by
+    /// [`IntegrityOpts::export_state`]. The result behaves as if all the
+    /// data input before the state was exported had been input into it, and
+    /// stays [`IntegrityOpts::resumable`].
+    pub fn restore_state(state: &str) -> Result<IntegrityOpts, Error> {
+        let mut opts = IntegrityOpts::new();
+        opts.resumable = true;
+        for entry in state.split_whitespace() {
+            let invalid = || Error::HasherStateError(format!("invalid hasher state `{}`", entry));
+            let (name, encoded) = entry.rsplit_once('-').ok_or_else(invalid)?;
+            let algorithm = name.parse::<Algorithm>().map_err(|_| invalid())?;
+            let bytes = BASE64_STANDARD.decode(encoded).map_err(|_| invalid())?;
+            let inner = algorithm
+                .implementation()
+                .and_then(|i| i.restore_hasher(&bytes))
+                .ok_or_else(invalid)?;
+            opts.hashers.push(Hasher { algorithm, inner });
+        }
+        opts.disturbed = true;
+        Ok(opts)
+    }
+
     /// Resets internal state for this IntegrityOpts.
     pub fn reset(&mut self) {
         self.hashers = vec![];
@@ -109,22 +287,7 @@ impl IntegrityOpts {
         let mut hashes = self
             .hashers
             .into_iter()
-            .map(|h| {
-                let (algorithm, data) = match h {
-                    Hasher::Sha1(h) => (Algorithm::Sha1, BASE64_STANDARD.encode(h.finalize())),
-                    Hasher::Sha256(h) => (Algorithm::Sha256, BASE64_STANDARD.encode(h.finalize())),
-                    Hasher::Sha384(h) => (Algorithm::Sha384, BASE64_STANDARD.encode(h.finalize())),
-                    Hasher::Sha512(h) => (Algorithm::Sha512, BASE64_STANDARD.encode(h.finalize())),
-                    Hasher::Xxh3(h) => (
-                        Algorithm::Xxh3,
-                        BASE64_STANDARD.encode(h.digest128().to_be_bytes()),
-                    ),
-                };
-                Hash {
-                    algorithm,
-                    digest: data,
-                }
-            })
+            .map(|h| Hash::from_bytes(h.algorithm, &h.inner.finalize()))
             .collect::<Vec<Hash>>();
         hashes.sort();
         Integrity { hashes }
@@ -190,4 +353,103 @@ mod tests {
             "sha256-uU0nuZNNPgilLlLX2n2r+sSE7+N6U4DukIj3rOLvzek= sha1-Kq5sNclPz7QV2+lfQIuc6R7oRu0="
         )
     }
+
+    #[test]
+    fn sha3_test() {
+        let result = IntegrityOpts::new()
+            .algorithm(Algorithm::Sha3_256)
+            .algorithm(Algorithm::Sha3_384)
+            .algorithm(Algorithm::Sha3_512)
+            .algorithm(Algorithm::Sha256)
+            .chain(b"hello world")
+            .result();
+        assert_eq!(
+            result.to_string(),
+            "sha3-512-hAAGZT6ayelRF6FckVyquBZikY6SXengBPd0/4LXB5pA1NJ7GzcmV8YdRtRwMEyIx4izpFJ60HTR3MvuXbqpmg== \
+             sha3-384-g7/yjd4bG/WBAHHGZDwI5bBb24Nu/9cLQD6o6gpjTcSZfrEFOqNZP1kPnGNjDdkL \
+             sha256-uU0nuZNNPgilLlLX2n2r+sSE7+N6U4DukIj3rOLvzek= \
+             sha3-256-ZEvMflZDcwQJmarInnYi88px+6HZcv2Uoxw7+/JOOTg="
+        )
+    }
+
+    #[cfg(feature = "blake3")]
+    #[test]
+    fn blake3_known_answers() {
+        let empty = IntegrityOpts::new().algorithm(Algorithm::Blake3).result();
+        assert_eq!(
+            empty.to_hex(),
+            (
+                Algorithm::Blake3,
+                String::from("af1349b9f5f9a1a6a0404dea36dcc9499bcb25c9adc112b7cc9a93cae41f3262")
+            )
+        );
+        let result = IntegrityOpts::new()
+            .algorithm(Algorithm::Blake3)
+            .algorithm(Algorithm::Sha1)
+            .chain(b"hello world")
+            .result();
+        assert_eq!(
+            result.to_string(),
+            "blake3-10mB76cKDIgLjYwZhdB128v2ebmaX5kU5ar5a4ManiQ= sha1-Kq5sNclPz7QV2+lfQIuc6R7oRu0="
+        );
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn parallel_test() {
+        let data = (0..200 * 1024)
+            .map(|i| (i % 253) as u8)
+            .collect::<Vec<u8>>();
+        let opts = IntegrityOpts::new()
+            .algorithm(Algorithm::Sha512)
+            .algorithm(Algorithm::Sha256)
+            .algorithm(Algorithm::Sha1)
+            .algorithm(Algorithm::Xxh3);
+        let serial = opts.clone().chain(&data).chain(b"tail").result();
+        let parallel = opts.parallel(true).chain(&data).chain(b"tail").result();
+        assert_eq!(serial, parallel);
+    }
+
+    #[test]
+    fn resume_test() {
+        let data = (0..5000).map(|i| (i % 7) as u8).collect::<Vec<u8>>();
+        let opts = || {
+            IntegrityOpts::new()
+                .algorithm(Algorithm::Sha512)
+                .algorithm(Algorithm::Sha384)
+                .algorithm(Algorithm::Sha256)
+                .algorithm(Algorithm::Xxh3)
+                .resumable(true)
+        };
+        let mut resumed = opts();
+        for chunk in data.chunks(777) {
+            resumed.input(chunk);
+            let state = resumed.export_state().unwrap();
+            resumed = IntegrityOpts::restore_state(&state).unwrap();
+        }
+        assert_eq!(resumed.result(), opts().chain(&data).result());
+    }
+
+    #[test]
+    fn resume_unsupported() {
+        use crate::Error;
+        let opts = IntegrityOpts::new().algorithm(Algorithm::Sha256);
+        assert!(matches!(
+            opts.export_state(),
+            Err(Error::HasherStateError(_))
+        ));
+        let opts = opts.algorithm(Algorithm::Sha1).resumable(true);
+        assert!(matches!(
+            opts.export_state(),
+            Err(Error::HasherStateError(_))
+        ));
+        assert!(matches!(
+            IntegrityOpts::restore_state("sha256-AQID"),
+            Err(Error::HasherStateError(_))
+        ));
+        assert!(matches!(
+            IntegrityOpts::restore_state("sha1-AQID"),
+            Err(Error::HasherStateError(_))
+        ));
+    }
 }
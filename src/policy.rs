@@ -0,0 +1,227 @@
+use crate::algorithm::Algorithm;
+use crate::integrity::Integrity;
+
+/**
+Rules for which algorithms an [`IntegrityChecker`](struct.IntegrityChecker.html)
+will trust.
+
+By default every known algorithm is allowed, which matches the behavior
+without a policy. Hashes whose algorithm isn't allowed are ignored when
+checking. If none of the hashes are allowed, the check fails with
+[`Error::AlgorithmNotAllowed`](enum.Error.html#variant.AlgorithmNotAllowed).
+
+# Examples
+
+```
+# use ssri::{Algorithm, Error, Integrity, IntegrityOpts, VerificationPolicy};
+let data = b"hello world";
+let sri = IntegrityOpts::new().algorithm(Algorithm::Sha1).chain(&data).result();
+let policy = VerificationPolicy::new().minimum(Algorithm::Sha256);
+assert!(matches!(
+    sri.check_with_policy(&data, &policy),
+    Err(Error::AlgorithmNotAllowed(_))
+));
+```
+*/
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VerificationPolicy {
+    minimum: Option<Algorithm>,
+    allowed: Option<Vec<Algorithm>>,
+    denied: Vec<Algorithm>,
+    non_cryptographic: bool,
+}
+
+impl Default for VerificationPolicy {
+    fn default() -> Self {
+        VerificationPolicy {
+            minimum: None,
+            allowed: None,
+            denied: Vec::new(),
+            non_cryptographic: true,
+        }
+    }
+}
+
+impl VerificationPolicy {
+    /// Creates a policy that allows every known algorithm.
+    pub fn new() -> VerificationPolicy {
+        Self::default()
+    }
+
+    /// Only allow algorithms at least as strong as `algorithm`, going by
+    /// [`Algorithm::strength`](enum.Algorithm.html#method.strength). Strengths
+    /// are compared in whole bytes, since built-in algorithms like `sha3-256`
+    /// and `blake3` rank a point or two below `sha256` only to break ties, so
+    /// `minimum(Sha256)` allows them too.
+    pub fn minimum(mut self, algorithm: Algorithm) -> Self {
+        self.minimum = Some(algorithm);
+        self
+    }
+
+    /// Adds `algorithm` to the allow list. Once anything has been added, only
+    /// algorithms on the list are allowed.
+    pub fn allow(mut self, algorithm: Algorithm) -> Self {
+        self.allowed.get_or_insert_with(Vec::new).push(algorithm);
+        self
+    }
+
+    /// Never allow `algorithm`, even if it's on the allow list.
+    pub fn deny(mut self, algorithm: Algorithm) -> Self {
+        self.denied.push(algorithm);
+        self
+    }
+
+    /// Whether non-cryptographic algorithms such as `xxh3` are allowed.
+    /// Defaults to `true`.
+    pub fn non_cryptographic(mut self, allowed: bool) -> Self {
+        self.non_cryptographic = allowed;
+        self
+    }
+
+    /// Whether this policy allows `algorithm`. Unknown algorithms are never
+    /// allowed, since they can't be checked.
+    pub fn allows(&self, algorithm: Algorithm) -> bool {
+        if matches!(algorithm, Algorithm::Unknown(_)) || self.denied.contains(&algorithm) {
+            return false;
+        }
+        if let Some(allowed) = &self.allowed {
+            if !allowed.contains(&algorithm) {
+                return false;
+            }
+        }
+        if let Some(minimum) = self.minimum {
+            if strength_bytes(algorithm) < strength_bytes(minimum) {
+                return false;
+            }
+        }
+        self.non_cryptographic || algorithm.is_cryptographic()
+    }
+
+    /// Returns a copy of `sri` with only the hashes this policy allows.
+    pub(crate) fn filter(&self, sri: &Integrity) -> Integrity {
+        Integrity {
+            hashes: sri
+                .hashes
+                .iter()
+                .filter(|h| self.allows(h.algorithm))
+                .cloned()
+                .collect(),
+        }
+    }
+}
+
+/// `algorithm`'s strength, rounded up to whole bytes.
+fn strength_bytes(algorithm: Algorithm) -> u32 {
+    algorithm.strength().saturating_add(7) / 8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::VerificationPolicy;
+    use crate::{
+        register_algorithm, Algorithm, AlgorithmHasher, HashAlgorithm, Integrity, IntegrityOpts,
+        UnknownTokens,
+    };
+
+    use digest::Digest;
+
+    #[derive(Clone)]
+    struct Truncated(sha2::Sha256);
+
+    impl AlgorithmHasher for Truncated {
+        fn update(&mut self, data: &[u8]) {
+            self.0.update(data);
+        }
+        fn finalize(self: Box<Self>) -> Vec<u8> {
+            let mut digest = self.0.finalize().to_vec();
+            digest.resize(64, 0);
+            digest
+        }
+        fn box_clone(&self) -> Box<dyn AlgorithmHasher> {
+            Box::new(self.clone())
+        }
+    }
+
+    /// A long digest that only claims the strength of `sha1`.
+    struct Padded;
+
+    impl HashAlgorithm for Padded {
+        fn name(&self) -> &str {
+            "padded-sha256"
+        }
+        fn digest_len(&self) -> usize {
+            64
+        }
+        fn strength(&self) -> u32 {
+            80
+        }
+        fn hasher(&self) -> Box<dyn AlgorithmHasher> {
+            Box::new(Truncated(sha2::Sha256::new()))
+        }
+    }
+
+    #[test]
+    fn default_allows_known() {
+        let policy = VerificationPolicy::new();
+        assert!(policy.allows(Algorithm::Sha1));
+        assert!(policy.allows(Algorithm::Xxh3));
+        assert!(policy.allows(Algorithm::Sha512));
+        let unknown = Integrity::parse_lenient("foo-YWJj", UnknownTokens::Keep).integrity;
+        assert!(!policy.allows(unknown.hashes[0].algorithm));
+    }
+
+    #[test]
+    fn minimum() {
+        let policy = VerificationPolicy::new().minimum(Algorithm::Sha256);
+        assert!(!policy.allows(Algorithm::Sha1));
+        assert!(!policy.allows(Algorithm::Xxh3));
+        assert!(policy.allows(Algorithm::Sha3_256));
+        assert!(policy.allows(Algorithm::Sha256));
+        assert!(policy.allows(Algorithm::Sha512));
+        #[cfg(feature = "blake3")]
+        assert!(policy.allows(Algorithm::Blake3));
+
+        let policy = VerificationPolicy::new().minimum(Algorithm::Sha512);
+        assert!(policy.allows(Algorithm::Sha3_512));
+        assert!(!policy.allows(Algorithm::Sha384));
+
+        let padded = register_algorithm(Padded).unwrap();
+        assert_eq!(padded.digest_len(), Algorithm::Sha512.digest_len());
+        assert!(!policy.allows(padded));
+        assert!(VerificationPolicy::new()
+            .minimum(Algorithm::Sha1)
+            .allows(padded));
+    }
+
+    #[test]
+    fn allow_and_deny() {
+        let policy = VerificationPolicy::new()
+            .allow(Algorithm::Sha256)
+            .allow(Algorithm::Sha512)
+            .deny(Algorithm::Sha512);
+        assert!(policy.allows(Algorithm::Sha256));
+        assert!(!policy.allows(Algorithm::Sha512));
+        assert!(!policy.allows(Algorithm::Sha384));
+    }
+
+    #[test]
+    fn non_cryptographic() {
+        let policy = VerificationPolicy::new().non_cryptographic(false);
+        assert!(!policy.allows(Algorithm::Xxh3));
+        assert!(policy.allows(Algorithm::Sha1));
+    }
+
+    #[test]
+    fn filter() {
+        let sri = IntegrityOpts::new()
+            .algorithm(Algorithm::Sha256)
+            .algorithm(Algorithm::Xxh3)
+            .chain(b"hello")
+            .result();
+        let filtered = VerificationPolicy::new()
+            .non_cryptographic(false)
+            .filter(&sri);
+        assert_eq!(filtered.hashes.len(), 1);
+        assert_eq!(filtered.hashes[0].algorithm, Algorithm::Sha256);
+    }
+}
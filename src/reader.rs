@@ -0,0 +1,197 @@
+use std::io::{self, Read};
+
+use crate::algorithm::Algorithm;
+use crate::errors::Error;
+use crate::integrity::Integrity;
+use crate::opts::IntegrityOpts;
+use crate::stream::StreamChecker;
+
+/**
+Wraps a [`Read`](std::io::Read), generating an [`Integrity`](struct.Integrity.html)
+for everything read through it, and optionally checking it against an
+expected one.
+
+Once the inner reader hits EOF, the data is verified. If it doesn't match, the
+final `read()` fails with an [`io::Error`](std::io::Error) of kind
+`InvalidData` that wraps an [`Error::IntegrityCheckError`](enum.Error.html).
+
+# Examples
+
+```
+# use ssri::{Integrity, IntegrityOpts, IntegrityReader};
+let data = b"hello world";
+let sri = Integrity::from(data);
+let mut reader = IntegrityReader::new(&data[..], IntegrityOpts::new()).expected(sri.clone());
+std::io::copy(&mut reader, &mut std::io::sink()).unwrap();
+assert_eq!(reader.integrity(), Some(&sri));
+assert_eq!(reader.bytes_read(), 11);
+```
+
+Getting at the integrity error on mismatch:
+
+```
+# use ssri::{Error, Integrity, IntegrityOpts, IntegrityReader};
+let sri = Integrity::from(b"hello world");
+let mut reader = IntegrityReader::new(&b"goodbye world"[..], IntegrityOpts::new()).expected(sri);
+let err = std::io::copy(&mut reader, &mut std::io::sink()).unwrap_err();
+let err = err.get_ref().and_then(|e| e.downcast_ref::<Error>()).unwrap();
+assert!(matches!(err, Error::IntegrityCheckError(..)));
+```
+*/
+#[derive(Debug)]
+pub struct IntegrityReader<R> {
+    inner: R,
+    checker: StreamChecker,
+}
+
+impl<R: Read> IntegrityReader<R> {
+    /// Creates a new `IntegrityReader` that hashes the data read from `inner`
+    /// with the algorithms configured on `opts`.
+    pub fn new(inner: R, opts: IntegrityOpts) -> IntegrityReader<R> {
+        IntegrityReader {
+            inner,
+            checker: StreamChecker::new(opts),
+        }
+    }
+
+    /// Checks the data against `sri` once the inner reader hits EOF. If it's
+    /// not already configured, the algorithm `sri` prefers is added to the
+    /// generated `Integrity`.
+    ///
+    /// Panics if called after the first successful read, even if it was empty.
+    pub fn expected(mut self, sri: Integrity) -> Self {
+        self.checker.expect(sri);
+        self
+    }
+
+    /// Fails with [`Error::TooManyBytes`](enum.Error.html#variant.TooManyBytes)
+    /// as soon as more than `len` bytes are read, and with
+    /// [`Error::TooFewBytes`](enum.Error.html#variant.TooFewBytes) at EOF if
+    /// fewer were. Both are wrapped in an [`io::ErrorKind::InvalidData`] error.
+    pub fn expected_len(mut self, len: u64) -> Self {
+        self.checker.expect_len(len);
+        self
+    }
+
+    /// Number of bytes read through this reader so far.
+    pub fn bytes_read(&self) -> u64 {
+        self.checker.bytes()
+    }
+
+    /// The `Integrity` generated from the data, once the inner reader has hit
+    /// EOF. This is available even if the check failed.
+    pub fn integrity(&self) -> Option<&Integrity> {
+        self.checker.integrity()
+    }
+
+    /// The algorithm the data was verified with, once the inner reader has hit
+    /// EOF and the check passed.
+    pub fn algorithm(&self) -> Option<Algorithm> {
+        self.checker.algorithm()
+    }
+
+    /// Unwraps this `IntegrityReader`, returning the inner reader.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: Read> Read for IntegrityReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.checker.is_finished() {
+            // Keep failing if someone reads past a failed check.
+            return self.checker.finish().map(|_| 0).map_err(invalid_data);
+        }
+        let read = self.inner.read(buf)?;
+        if read == 0 && !buf.is_empty() {
+            self.checker.finish().map_err(invalid_data)?;
+        }
+        self.checker.input(&buf[..read]).map_err(invalid_data)?;
+        Ok(read)
+    }
+}
+
+pub(crate) fn invalid_data(err: Error) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, err)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{self, Read};
+
+    use super::IntegrityReader;
+    use crate::{Algorithm, Error, Integrity, IntegrityOpts};
+
+    #[test]
+    fn passes_through() {
+        let sri = Integrity::from(b"hello world");
+        let mut reader =
+            IntegrityReader::new(&b"hello world"[..], IntegrityOpts::new()).expected(sri.clone());
+        let mut out = Vec::new();
+        reader.read_to_end(&mut out).unwrap();
+        assert_eq!(out, b"hello world");
+        assert_eq!(reader.bytes_read(), 11);
+        assert_eq!(reader.integrity(), Some(&sri));
+        assert_eq!(reader.algorithm(), Some(Algorithm::Sha256));
+    }
+
+    #[test]
+    fn mismatch() {
+        let sri = Integrity::from(b"hello world");
+        let mut reader =
+            IntegrityReader::new(&b"hello world!"[..], IntegrityOpts::new()).expected(sri.clone());
+        let err = io::copy(&mut reader, &mut io::sink()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        let inner = err.into_inner().unwrap().downcast::<Error>().unwrap();
+        assert_eq!(
+            *inner,
+            Error::IntegrityCheckError(sri, Integrity::from(b"hello world!"))
+        );
+        assert_eq!(reader.bytes_read(), 12);
+        assert_eq!(reader.integrity(), Some(&Integrity::from(b"hello world!")));
+        assert_eq!(reader.algorithm(), None);
+        // Reading again keeps failing.
+        assert!(reader.read(&mut [0; 8]).is_err());
+    }
+
+    #[test]
+    #[should_panic(expected = "input has started")]
+    fn expected_after_read() {
+        let opts = IntegrityOpts::new().algorithm(Algorithm::Sha256);
+        let mut reader = IntegrityReader::new(&b""[..], opts);
+        assert_eq!(reader.read(&mut [0; 8]).unwrap(), 0);
+        let _ = reader.expected(Integrity::from(b""));
+    }
+
+    #[test]
+    fn expected_len() {
+        let sri = Integrity::from(b"hello world");
+        let mut reader = IntegrityReader::new(&b"hello world!"[..], IntegrityOpts::new())
+            .expected(sri.clone())
+            .expected_len(11);
+        let err = reader.read(&mut [0; 64]).unwrap_err();
+        let inner = err.into_inner().unwrap().downcast::<Error>().unwrap();
+        assert_eq!(*inner, Error::TooManyBytes(11, 12));
+        assert!(reader.read(&mut [0; 8]).is_err());
+
+        let mut reader = IntegrityReader::new(&b"hello"[..], IntegrityOpts::new())
+            .expected(sri)
+            .expected_len(11);
+        let err = io::copy(&mut reader, &mut io::sink()).unwrap_err();
+        let inner = err.into_inner().unwrap().downcast::<Error>().unwrap();
+        assert_eq!(*inner, Error::TooFewBytes(11, 5));
+    }
+
+    #[test]
+    fn expected_empty() {
+        let empty = Integrity { hashes: vec![] };
+        let mut reader =
+            IntegrityReader::new(&b"hello world"[..], IntegrityOpts::new()).expected(empty.clone());
+        let err = io::copy(&mut reader, &mut io::sink()).unwrap_err();
+        let inner = err.into_inner().unwrap().downcast::<Error>().unwrap();
+        assert_eq!(
+            *inner,
+            Error::IntegrityCheckError(empty, Integrity { hashes: vec![] })
+        );
+    }
+}
@@ -0,0 +1,504 @@
+use std::fmt;
+use std::sync::RwLock;
+
+use digest::Digest;
+use once_cell::sync::Lazy;
+
+use crate::algorithm::Algorithm;
+use crate::errors::Error;
+use crate::resumable::{Sha256Hasher, Sha512Hasher, Xxh3Hasher};
+
+/**
+A hash algorithm that `ssri` can generate and check integrity strings with.
+
+All of the built-in [`Algorithm`](enum.Algorithm.html)s are implemented on top
+of this trait. Implement it yourself and pass it to
+[`register_algorithm`](fn.register_algorithm.html) to teach `ssri` about an
+in-house digest without having to patch the crate.
+
+The `strength` rank decides which algorithm
+[`Integrity::pick_algorithm`](struct.Integrity.html#method.pick_algorithm)
+prefers. The built-in ranks are roughly the digest size in bits, with the SHA-2
+family winning ties:
+
+| Algorithm  | Strength |
+|------------|----------|
+| `sha512`   | 512      |
+| `sha3-512` | 511      |
+| `sha384`   | 384      |
+| `sha3-384` | 383      |
+| `sha256`   | 256      |
+| `sha3-256` | 255      |
+| `blake3`   | 254      |
+| `sha1`     | 80       |
+| `xxh3`     | 0        |
+
+# Example
+
+```
+use ssri::{register_algorithm, AlgorithmHasher, HashAlgorithm, IntegrityOpts};
+
+#[derive(Clone)]
+struct Sum8(u8);
+
+impl AlgorithmHasher for Sum8 {
+    fn update(&mut self, data: &[u8]) {
+        self.0 = data.iter().fold(self.0, |acc, b| acc.wrapping_add(*b));
+    }
+    fn finalize(self: Box<Self>) -> Vec<u8> {
+        vec![self.0]
+    }
+    fn box_clone(&self) -> Box<dyn AlgorithmHasher> {
+        Box::new(self.clone())
+    }
+}
+
+struct Sum8Algorithm;
+
+impl HashAlgorithm for Sum8Algorithm {
+    fn name(&self) -> &str {
+        "sum8"
+    }
+    fn digest_len(&self) -> usize {
+        1
+    }
+    fn strength(&self) -> u32 {
+        0
+    }
+    fn hasher(&self) -> Box<dyn AlgorithmHasher> {
+        Box::new(Sum8(0))
+    }
+}
+
+let sum8 = register_algorithm(Sum8Algorithm).unwrap();
+let sri = IntegrityOpts::new().algorithm(sum8).chain(b"\x01\x02").result();
+assert_eq!(sri.to_string(), "sum8-Aw==");
+assert_eq!(sri.to_string().parse::<ssri::Integrity>().unwrap(), sri);
+```
+*/
+pub trait HashAlgorithm: Send + Sync + 'static {
+    /// Name of the algorithm, as used in integrity strings (`sha256`, `blake3`, ...).
+    fn name(&self) -> &str;
+    /// Number of bytes in a digest produced by this algorithm.
+    fn digest_len(&self) -> usize;
+    /// Relative strength of this algorithm. Higher ranks are preferred.
+    fn strength(&self) -> u32;
+    /// Creates a new hasher with empty state.
+    fn hasher(&self) -> Box<dyn AlgorithmHasher>;
+    /// Whether this algorithm is meant to resist deliberate collisions.
+    /// Defaults to `true`. Checksums like `xxh3` return `false`, so a
+    /// [`VerificationPolicy`](struct.VerificationPolicy.html) can reject them.
+    fn is_cryptographic(&self) -> bool {
+        true
+    }
+    /// Creates a new hasher whose state can be exported with
+    /// [`AlgorithmHasher::export_state`](trait.AlgorithmHasher.html#method.export_state).
+    /// This is what [`IntegrityOpts::resumable`](struct.IntegrityOpts.html#method.resumable)
+    /// uses, so algorithms can keep a faster hasher as the default. Defaults
+    /// to [`HashAlgorithm::hasher`](trait.HashAlgorithm.html#tymethod.hasher).
+    fn resumable_hasher(&self) -> Box<dyn AlgorithmHasher> {
+        self.hasher()
+    }
+    /// Recreates a hasher from a state previously returned by
+    /// [`AlgorithmHasher::export_state`](trait.AlgorithmHasher.html#method.export_state),
+    /// or returns `None` if the state isn't valid. Algorithms that don't
+    /// support this return `None` for everything, which is the default.
+    fn restore_hasher(&self, state: &[u8]) -> Option<Box<dyn AlgorithmHasher>> {
+        let _ = state;
+        None
+    }
+}
+
+/// Running hash state for a [`HashAlgorithm`](trait.HashAlgorithm.html).
+pub trait AlgorithmHasher: Send {
+    /// Feeds some data into the hasher.
+    fn update(&mut self, data: &[u8]);
+    /// Consumes the hasher and returns the raw digest bytes.
+    fn finalize(self: Box<Self>) -> Vec<u8>;
+    /// Clones the running state of this hasher.
+    fn box_clone(&self) -> Box<dyn AlgorithmHasher>;
+    /// Serializes the running state of this hasher, so it can be restored
+    /// with [`HashAlgorithm::restore_hasher`](trait.HashAlgorithm.html#method.restore_hasher),
+    /// possibly in another process. Defaults to `None`, meaning the state
+    /// can't be exported.
+    fn export_state(&self) -> Option<Vec<u8>> {
+        None
+    }
+}
+
+/**
+Handle to a user-defined algorithm, wrapped by
+[`Algorithm::Custom`](enum.Algorithm.html#variant.Custom).
+
+These can only be obtained through
+[`register_algorithm`](fn.register_algorithm.html) or by parsing the name of an
+algorithm that was previously registered.
+*/
+#[derive(Clone, Copy)]
+pub struct CustomAlgorithm(&'static dyn HashAlgorithm);
+
+impl CustomAlgorithm {
+    pub(crate) fn implementation(&self) -> &'static dyn HashAlgorithm {
+        self.0
+    }
+}
+
+impl fmt::Debug for CustomAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("CustomAlgorithm")
+            .field(&self.0.name())
+            .finish()
+    }
+}
+
+impl PartialEq for CustomAlgorithm {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.name() == other.0.name()
+    }
+}
+
+impl Eq for CustomAlgorithm {}
+
+impl std::hash::Hash for CustomAlgorithm {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.0.name().hash(state)
+    }
+}
+
+static REGISTRY: Lazy<RwLock<Vec<&'static dyn HashAlgorithm>>> =
+    Lazy::new(|| RwLock::new(Vec::new()));
+
+/**
+Registers a user-defined [`HashAlgorithm`](trait.HashAlgorithm.html), making
+it available to [`IntegrityOpts`](struct.IntegrityOpts.html),
+[`IntegrityChecker`](struct.IntegrityChecker.html) and `Integrity` parsing for
+the rest of the program.
+
+Names may only contain ASCII alphanumerics, `-` and `_`, and can't clash with a
+built-in or previously registered algorithm.
+*/
+pub fn register_algorithm<A: HashAlgorithm>(algorithm: A) -> Result<Algorithm, Error> {
+    let name = algorithm.name();
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        return Err(Error::AlgorithmRegistrationError(format!(
+            "invalid algorithm name {:?}",
+            name
+        )));
+    }
+    let mut registry = REGISTRY.write().unwrap_or_else(|e| e.into_inner());
+    if builtin(name).is_some() || registry.iter().any(|a| a.name() == name) {
+        return Err(Error::AlgorithmRegistrationError(format!(
+            "an algorithm named {:?} already exists",
+            name
+        )));
+    }
+    let algorithm: &'static dyn HashAlgorithm = Box::leak(Box::new(algorithm));
+    registry.push(algorithm);
+    Ok(Algorithm::Custom(CustomAlgorithm(algorithm)))
+}
+
+/// Looks up a built-in or registered algorithm by name.
+pub(crate) fn lookup(name: &str) -> Option<Algorithm> {
+    builtin(name).or_else(|| {
+        REGISTRY
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+            .find(|a| a.name() == name)
+            .map(|a| Algorithm::Custom(CustomAlgorithm(*a)))
+    })
+}
+
+fn builtin(name: &str) -> Option<Algorithm> {
+    match name {
+        "sha1" => Some(Algorithm::Sha1),
+        "sha256" => Some(Algorithm::Sha256),
+        "sha384" => Some(Algorithm::Sha384),
+        "sha512" => Some(Algorithm::Sha512),
+        "sha3-256" => Some(Algorithm::Sha3_256),
+        "sha3-384" => Some(Algorithm::Sha3_384),
+        "sha3-512" => Some(Algorithm::Sha3_512),
+        #[cfg(feature = "blake3")]
+        "blake3" => Some(Algorithm::Blake3),
+        "xxh3" => Some(Algorithm::Xxh3),
+        _ => None,
+    }
+}
+
+pub(crate) struct Builtin {
+    name: &'static str,
+    digest_len: usize,
+    strength: u32,
+    cryptographic: bool,
+    hasher: HasherFn,
+    /// Slower hashers whose state can be exported, and how to restore them.
+    resumable: Option<(HasherFn, RestoreFn)>,
+}
+
+type HasherFn = fn() -> Box<dyn AlgorithmHasher>;
+type RestoreFn = fn(&[u8]) -> Option<Box<dyn AlgorithmHasher>>;
+
+impl HashAlgorithm for Builtin {
+    fn name(&self) -> &str {
+        self.name
+    }
+    fn digest_len(&self) -> usize {
+        self.digest_len
+    }
+    fn strength(&self) -> u32 {
+        self.strength
+    }
+    fn hasher(&self) -> Box<dyn AlgorithmHasher> {
+        (self.hasher)()
+    }
+    fn is_cryptographic(&self) -> bool {
+        self.cryptographic
+    }
+    fn resumable_hasher(&self) -> Box<dyn AlgorithmHasher> {
+        match self.resumable {
+            Some((hasher, _)) => hasher(),
+            None => self.hasher(),
+        }
+    }
+    fn restore_hasher(&self, state: &[u8]) -> Option<Box<dyn AlgorithmHasher>> {
+        self.resumable.and_then(|(_, restore)| restore(state))
+    }
+}
+
+pub(crate) static SHA1: Builtin = Builtin {
+    name: "sha1",
+    digest_len: 20,
+    strength: 80,
+    cryptographic: true,
+    hasher: digest_hasher::<sha1::Sha1>,
+    resumable: None,
+};
+pub(crate) static SHA256: Builtin = Builtin {
+    name: "sha256",
+    digest_len: 32,
+    strength: 256,
+    cryptographic: true,
+    hasher: digest_hasher::<sha2::Sha256>,
+    resumable: Some((Sha256Hasher::boxed, Sha256Hasher::restore)),
+};
+pub(crate) static SHA384: Builtin = Builtin {
+    name: "sha384",
+    digest_len: 48,
+    strength: 384,
+    cryptographic: true,
+    hasher: digest_hasher::<sha2::Sha384>,
+    resumable: Some((Sha512Hasher::boxed_384, Sha512Hasher::restore_384)),
+};
+pub(crate) static SHA512: Builtin = Builtin {
+    name: "sha512",
+    digest_len: 64,
+    strength: 512,
+    cryptographic: true,
+    hasher: digest_hasher::<sha2::Sha512>,
+    resumable: Some((Sha512Hasher::boxed_512, Sha512Hasher::restore_512)),
+};
+pub(crate) static SHA3_256: Builtin = Builtin {
+    name: "sha3-256",
+    digest_len: 32,
+    strength: 255,
+    cryptographic: true,
+    hasher: digest_hasher::<sha3::Sha3_256>,
+    resumable: None,
+};
+pub(crate) static SHA3_384: Builtin = Builtin {
+    name: "sha3-384",
+    digest_len: 48,
+    strength: 383,
+    cryptographic: true,
+    hasher: digest_hasher::<sha3::Sha3_384>,
+    resumable: None,
+};
+pub(crate) static SHA3_512: Builtin = Builtin {
+    name: "sha3-512",
+    digest_len: 64,
+    strength: 511,
+    cryptographic: true,
+    hasher: digest_hasher::<sha3::Sha3_512>,
+    resumable: None,
+};
+#[cfg(feature = "blake3")]
+pub(crate) static BLAKE3: Builtin = Builtin {
+    name: "blake3",
+    digest_len: 32,
+    strength: 254,
+    cryptographic: true,
+    hasher: || Box::new(Blake3Hasher(Box::new(blake3::Hasher::new()))),
+    resumable: None,
+};
+pub(crate) static XXH3: Builtin = Builtin {
+    name: "xxh3",
+    digest_len: 16,
+    strength: 0,
+    cryptographic: false,
+    hasher: || Box::new(XxhashHasher(Box::new(xxhash_rust::xxh3::Xxh3::new()))),
+    resumable: Some((Xxh3Hasher::boxed, Xxh3Hasher::restore)),
+};
+
+#[derive(Clone)]
+struct DigestHasher<D>(D);
+
+fn digest_hasher<D: Digest + Clone + Send + 'static>() -> Box<dyn AlgorithmHasher> {
+    Box::new(DigestHasher(D::new()))
+}
+
+impl<D: Digest + Clone + Send + 'static> AlgorithmHasher for DigestHasher<D> {
+    fn update(&mut self, data: &[u8]) {
+        Digest::update(&mut self.0, data);
+    }
+    fn finalize(self: Box<Self>) -> Vec<u8> {
+        self.0.finalize().to_vec()
+    }
+    fn box_clone(&self) -> Box<dyn AlgorithmHasher> {
+        Box::new(self.clone())
+    }
+}
+
+#[cfg(feature = "blake3")]
+#[derive(Clone)]
+struct Blake3Hasher(Box<blake3::Hasher>);
+
+#[cfg(feature = "blake3")]
+impl AlgorithmHasher for Blake3Hasher {
+    fn update(&mut self, data: &[u8]) {
+        self.0.update(data);
+    }
+    fn finalize(self: Box<Self>) -> Vec<u8> {
+        self.0.finalize().as_bytes().to_vec()
+    }
+    fn box_clone(&self) -> Box<dyn AlgorithmHasher> {
+        Box::new(self.clone())
+    }
+}
+
+#[derive(Clone)]
+struct XxhashHasher(Box<xxhash_rust::xxh3::Xxh3>);
+
+impl AlgorithmHasher for XxhashHasher {
+    fn update(&mut self, data: &[u8]) {
+        self.0.update(data);
+    }
+    fn finalize(self: Box<Self>) -> Vec<u8> {
+        self.0.digest128().to_be_bytes().to_vec()
+    }
+    fn box_clone(&self) -> Box<dyn AlgorithmHasher> {
+        Box::new(self.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{register_algorithm, AlgorithmHasher, HashAlgorithm};
+    use crate::{Algorithm, Error, Integrity, IntegrityChecker, IntegrityOpts};
+
+    use digest::Digest;
+
+    #[derive(Clone)]
+    struct KeyedSha256(sha2::Sha256);
+
+    impl AlgorithmHasher for KeyedSha256 {
+        fn update(&mut self, data: &[u8]) {
+            self.0.update(data);
+        }
+        fn finalize(self: Box<Self>) -> Vec<u8> {
+            self.0.finalize().to_vec()
+        }
+        fn box_clone(&self) -> Box<dyn AlgorithmHasher> {
+            Box::new(self.clone())
+        }
+    }
+
+    struct Keyed {
+        name: &'static str,
+        strength: u32,
+    }
+
+    impl HashAlgorithm for Keyed {
+        fn name(&self) -> &str {
+            self.name
+        }
+        fn digest_len(&self) -> usize {
+            32
+        }
+        fn strength(&self) -> u32 {
+            self.strength
+        }
+        fn hasher(&self) -> Box<dyn AlgorithmHasher> {
+            Box::new(KeyedSha256(sha2::Sha256::new().chain_update(b"secret key")))
+        }
+    }
+
+    #[test]
+    fn custom_roundtrip() {
+        let keyed = register_algorithm(Keyed {
+            name: "keyed-sha256",
+            strength: 300,
+        })
+        .unwrap();
+        assert_eq!(keyed.to_string(), "keyed-sha256");
+        assert_eq!("keyed-sha256".parse::<Algorithm>().unwrap(), keyed);
+
+        let sri = IntegrityOpts::new()
+            .algorithm(Algorithm::Sha256)
+            .algorithm(keyed)
+            .chain(b"hello world")
+            .result();
+        assert_eq!(sri.pick_algorithm(), keyed);
+        assert_ne!(sri.hashes[0].as_bytes(), sri.hashes[1].as_bytes());
+
+        let parsed: Integrity = sri.to_string().parse().unwrap();
+        assert_eq!(parsed, sri);
+        let result = IntegrityChecker::new(parsed).chain(b"hello world").result();
+        assert_eq!(result.unwrap(), keyed);
+    }
+
+    #[test]
+    fn custom_ordering() {
+        let weak = register_algorithm(Keyed {
+            name: "weak-keyed",
+            strength: 100,
+        })
+        .unwrap();
+        let mut arr = [Algorithm::Sha1, weak, Algorithm::Sha256, Algorithm::Xxh3];
+        arr.sort_unstable();
+        assert_eq!(
+            arr,
+            [Algorithm::Sha256, weak, Algorithm::Sha1, Algorithm::Xxh3]
+        );
+    }
+
+    #[test]
+    fn duplicate_names() {
+        let keyed = Keyed {
+            name: "sha256",
+            strength: 1,
+        };
+        assert!(matches!(
+            register_algorithm(keyed),
+            Err(Error::AlgorithmRegistrationError(_))
+        ));
+        let first = Keyed {
+            name: "dupe",
+            strength: 1,
+        };
+        let second = Keyed {
+            name: "dupe",
+            strength: 2,
+        };
+        assert!(register_algorithm(first).is_ok());
+        assert!(register_algorithm(second).is_err());
+        let bad = Keyed {
+            name: "bad name",
+            strength: 1,
+        };
+        assert!(register_algorithm(bad).is_err());
+    }
+}
@@ -0,0 +1,603 @@
+//! Hashers whose running state can be exported and restored, so a partial
+//! hash can be finished in another process.
+//!
+//! The SHA-2 hashers drive `sha2`'s block functions directly, which is all
+//! its own hashers do, but keep the state where we can get at it. The xxh3
+//! hasher is a port of the streaming 128-bit algorithm from `xxhash-rust`,
+//! since that crate doesn't expose its state either.
+//!
+//! Exported states start with a version byte, followed by the fields in
+//! big-endian order, and finally any buffered input.
+
+use std::convert::TryInto;
+use std::mem;
+
+use digest::generic_array::GenericArray;
+
+use crate::registry::AlgorithmHasher;
+
+const STATE_VERSION: u8 = 1;
+
+/// Splits the version byte off an exported state.
+fn versioned(state: &[u8]) -> Option<&[u8]> {
+    match state.split_first() {
+        Some((&STATE_VERSION, rest)) => Some(rest),
+        _ => None,
+    }
+}
+
+const SHA256_IV: [u32; 8] = [
+    0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
+];
+
+#[derive(Clone)]
+pub(crate) struct Sha256Hasher {
+    state: [u32; 8],
+    buffer: Vec<u8>,
+    len: u64,
+}
+
+impl Sha256Hasher {
+    pub(crate) fn boxed() -> Box<dyn AlgorithmHasher> {
+        Box::new(Sha256Hasher {
+            state: SHA256_IV,
+            buffer: Vec::with_capacity(64),
+            len: 0,
+        })
+    }
+
+    pub(crate) fn restore(state: &[u8]) -> Option<Box<dyn AlgorithmHasher>> {
+        let state = versioned(state)?;
+        if state.len() < 40 {
+            return None;
+        }
+        let (words, rest) = state.split_at(32);
+        let (len, buffer) = rest.split_at(8);
+        let len = u64::from_be_bytes(len.try_into().ok()?);
+        if len % 64 != buffer.len() as u64 {
+            return None;
+        }
+        let mut hasher = Sha256Hasher {
+            state: [0; 8],
+            buffer: buffer.to_vec(),
+            len,
+        };
+        for (word, bytes) in hasher.state.iter_mut().zip(words.chunks_exact(4)) {
+            *word = u32::from_be_bytes(bytes.try_into().ok()?);
+        }
+        Some(Box::new(hasher))
+    }
+
+    fn compress(&mut self, block: &[u8]) {
+        sha2::compress256(
+            &mut self.state,
+            std::slice::from_ref(GenericArray::from_slice(block)),
+        );
+    }
+}
+
+impl AlgorithmHasher for Sha256Hasher {
+    fn update(&mut self, mut data: &[u8]) {
+        self.len += data.len() as u64;
+        if !self.buffer.is_empty() {
+            let take = data.len().min(64 - self.buffer.len());
+            self.buffer.extend_from_slice(&data[..take]);
+            data = &data[take..];
+            if self.buffer.len() < 64 {
+                return;
+            }
+            let block = std::mem::take(&mut self.buffer);
+            self.compress(&block);
+            self.buffer = block;
+            self.buffer.clear();
+        }
+        let mut blocks = data.chunks_exact(64);
+        for block in &mut blocks {
+            self.compress(block);
+        }
+        self.buffer.extend_from_slice(blocks.remainder());
+    }
+
+    fn finalize(mut self: Box<Self>) -> Vec<u8> {
+        let bits = self.len.wrapping_mul(8);
+        let mut tail = std::mem::take(&mut self.buffer);
+        tail.push(0x80);
+        while tail.len() % 64 != 56 {
+            tail.push(0);
+        }
+        tail.extend_from_slice(&bits.to_be_bytes());
+        for block in tail.chunks_exact(64) {
+            self.compress(block);
+        }
+        self.state.iter().flat_map(|w| w.to_be_bytes()).collect()
+    }
+
+    fn box_clone(&self) -> Box<dyn AlgorithmHasher> {
+        Box::new(self.clone())
+    }
+
+    fn export_state(&self) -> Option<Vec<u8>> {
+        let mut out = vec![STATE_VERSION];
+        for word in &self.state {
+            out.extend_from_slice(&word.to_be_bytes());
+        }
+        out.extend_from_slice(&self.len.to_be_bytes());
+        out.extend_from_slice(&self.buffer);
+        Some(out)
+    }
+}
+
+const SHA384_IV: [u64; 8] = [
+    0xcbbb9d5dc1059ed8,
+    0x629a292a367cd507,
+    0x9159015a3070dd17,
+    0x152fecd8f70e5939,
+    0x67332667ffc00b31,
+    0x8eb44a8768581511,
+    0xdb0c2e0d64f98fa7,
+    0x47b5481dbefa4fa4,
+];
+
+const SHA512_IV: [u64; 8] = [
+    0x6a09e667f3bcc908,
+    0xbb67ae8584caa73b,
+    0x3c6ef372fe94f82b,
+    0xa54ff53a5f1d36f1,
+    0x510e527fade682d1,
+    0x9b05688c2b3e6c1f,
+    0x1f83d9abfb41bd6b,
+    0x5be0cd19137e2179,
+];
+
+/// SHA-384 and SHA-512, which only differ in their initial state and how much
+/// of the final state makes up the digest.
+#[derive(Clone)]
+pub(crate) struct Sha512Hasher {
+    state: [u64; 8],
+    buffer: Vec<u8>,
+    len: u128,
+    digest_len: usize,
+}
+
+impl Sha512Hasher {
+    pub(crate) fn boxed_384() -> Box<dyn AlgorithmHasher> {
+        Box::new(Sha512Hasher::new(SHA384_IV, 48))
+    }
+
+    pub(crate) fn boxed_512() -> Box<dyn AlgorithmHasher> {
+        Box::new(Sha512Hasher::new(SHA512_IV, 64))
+    }
+
+    pub(crate) fn restore_384(state: &[u8]) -> Option<Box<dyn AlgorithmHasher>> {
+        Sha512Hasher::restore(state, 48)
+    }
+
+    pub(crate) fn restore_512(state: &[u8]) -> Option<Box<dyn AlgorithmHasher>> {
+        Sha512Hasher::restore(state, 64)
+    }
+
+    fn new(state: [u64; 8], digest_len: usize) -> Sha512Hasher {
+        Sha512Hasher {
+            state,
+            buffer: Vec::with_capacity(128),
+            len: 0,
+            digest_len,
+        }
+    }
+
+    fn restore(state: &[u8], digest_len: usize) -> Option<Box<dyn AlgorithmHasher>> {
+        let state = versioned(state)?;
+        if state.len() < 80 {
+            return None;
+        }
+        let (words, rest) = state.split_at(64);
+        let (len, buffer) = rest.split_at(16);
+        let len = u128::from_be_bytes(len.try_into().ok()?);
+        if len % 128 != buffer.len() as u128 {
+            return None;
+        }
+        let mut hasher = Sha512Hasher::new([0; 8], digest_len);
+        hasher.buffer.extend_from_slice(buffer);
+        hasher.len = len;
+        for (word, bytes) in hasher.state.iter_mut().zip(words.chunks_exact(8)) {
+            *word = u64::from_be_bytes(bytes.try_into().ok()?);
+        }
+        Some(Box::new(hasher))
+    }
+
+    fn compress(&mut self, block: &[u8]) {
+        sha2::compress512(
+            &mut self.state,
+            std::slice::from_ref(GenericArray::from_slice(block)),
+        );
+    }
+}
+
+impl AlgorithmHasher for Sha512Hasher {
+    fn update(&mut self, mut data: &[u8]) {
+        self.len += data.len() as u128;
+        if !self.buffer.is_empty() {
+            let take = data.len().min(128 - self.buffer.len());
+            self.buffer.extend_from_slice(&data[..take]);
+            data = &data[take..];
+            if self.buffer.len() < 128 {
+                return;
+            }
+            let block = std::mem::take(&mut self.buffer);
+            self.compress(&block);
+            self.buffer = block;
+            self.buffer.clear();
+        }
+        let mut blocks = data.chunks_exact(128);
+        for block in &mut blocks {
+            self.compress(block);
+        }
+        self.buffer.extend_from_slice(blocks.remainder());
+    }
+
+    fn finalize(mut self: Box<Self>) -> Vec<u8> {
+        let bits = self.len.wrapping_mul(8);
+        let mut tail = std::mem::take(&mut self.buffer);
+        tail.push(0x80);
+        while tail.len() % 128 != 112 {
+            tail.push(0);
+        }
+        tail.extend_from_slice(&bits.to_be_bytes());
+        for block in tail.chunks_exact(128) {
+            self.compress(block);
+        }
+        let mut digest = self
+            .state
+            .iter()
+            .flat_map(|w| w.to_be_bytes())
+            .collect::<Vec<u8>>();
+        digest.truncate(self.digest_len);
+        digest
+    }
+
+    fn box_clone(&self) -> Box<dyn AlgorithmHasher> {
+        Box::new(self.clone())
+    }
+
+    fn export_state(&self) -> Option<Vec<u8>> {
+        let mut out = vec![STATE_VERSION];
+        for word in &self.state {
+            out.extend_from_slice(&word.to_be_bytes());
+        }
+        out.extend_from_slice(&self.len.to_be_bytes());
+        out.extend_from_slice(&self.buffer);
+        Some(out)
+    }
+}
+
+const XXH_PRIME32_1: u64 = 0x9E3779B1;
+const XXH_PRIME32_2: u64 = 0x85EBCA77;
+const XXH_PRIME32_3: u64 = 0xC2B2AE3D;
+const XXH_PRIME64_1: u64 = 0x9E3779B185EBCA87;
+const XXH_PRIME64_2: u64 = 0xC2B2AE3D27D4EB4F;
+const XXH_PRIME64_3: u64 = 0x165667B19E3779F9;
+const XXH_PRIME64_4: u64 = 0x85EBCA77C2B2AE63;
+const XXH_PRIME64_5: u64 = 0x27D4EB2F165667C5;
+
+const XXH3_SECRET: [u8; 192] = [
+    0xb8, 0xfe, 0x6c, 0x39, 0x23, 0xa4, 0x4b, 0xbe, 0x7c, 0x01, 0x81, 0x2c, 0xf7, 0x21, 0xad, 0x1c,
+    0xde, 0xd4, 0x6d, 0xe9, 0x83, 0x90, 0x97, 0xdb, 0x72, 0x40, 0xa4, 0xa4, 0xb7, 0xb3, 0x67, 0x1f,
+    0xcb, 0x79, 0xe6, 0x4e, 0xcc, 0xc0, 0xe5, 0x78, 0x82, 0x5a, 0xd0, 0x7d, 0xcc, 0xff, 0x72, 0x21,
+    0xb8, 0x08, 0x46, 0x74, 0xf7, 0x43, 0x24, 0x8e, 0xe0, 0x35, 0x90, 0xe6, 0x81, 0x3a, 0x26, 0x4c,
+    0x3c, 0x28, 0x52, 0xbb, 0x91, 0xc3, 0x00, 0xcb, 0x88, 0xd0, 0x65, 0x8b, 0x1b, 0x53, 0x2e, 0xa3,
+    0x71, 0x64, 0x48, 0x97, 0xa2, 0x0d, 0xf9, 0x4e, 0x38, 0x19, 0xef, 0x46, 0xa9, 0xde, 0xac, 0xd8,
+    0xa8, 0xfa, 0x76, 0x3f, 0xe3, 0x9c, 0x34, 0x3f, 0xf9, 0xdc, 0xbb, 0xc7, 0xc7, 0x0b, 0x4f, 0x1d,
+    0x8a, 0x51, 0xe0, 0x4b, 0xcd, 0xb4, 0x59, 0x31, 0xc8, 0x9f, 0x7e, 0xc9, 0xd9, 0x78, 0x73, 0x64,
+    0xea, 0xc5, 0xac, 0x83, 0x34, 0xd3, 0xeb, 0xc3, 0xc5, 0x81, 0xa0, 0xff, 0xfa, 0x13, 0x63, 0xeb,
+    0x17, 0x0d, 0xdd, 0x51, 0xb7, 0xf0, 0xda, 0x49, 0xd3, 0x16, 0x55, 0x26, 0x29, 0xd4, 0x68, 0x9e,
+    0x2b, 0x16, 0xbe, 0x58, 0x7d, 0x47, 0xa1, 0xfc, 0x8f, 0xf8, 0xb8, 0xd1, 0x7a, 0xd0, 0x31, 0xce,
+    0x45, 0xcb, 0x3a, 0x8f, 0x95, 0x16, 0x04, 0x28, 0xaf, 0xd7, 0xfb, 0xca, 0xbb, 0x4b, 0x40, 0x7e,
+];
+
+const XXH3_INITIAL_ACC: [u64; 8] = [
+    XXH_PRIME32_3,
+    XXH_PRIME64_1,
+    XXH_PRIME64_2,
+    XXH_PRIME64_3,
+    XXH_PRIME64_4,
+    XXH_PRIME32_2,
+    XXH_PRIME64_5,
+    XXH_PRIME32_1,
+];
+
+/// Inputs up to this size are hashed with a different, one-shot algorithm.
+const XXH3_MID_SIZE_MAX: u64 = 240;
+const XXH3_STRIPES_PER_BLOCK: u8 = 16;
+
+/// 128-bit xxh3 with the default secret and seed.
+#[derive(Clone)]
+pub(crate) struct Xxh3Hasher {
+    acc: [u64; 8],
+    /// Stripes accumulated since the last scramble.
+    stripes: u8,
+    len: u64,
+    /// The last stripe that was accumulated. The final stripe can overlap it.
+    last_stripe: [u8; 64],
+    /// Input that hasn't been accumulated. This is all of it until the input
+    /// is too long for the one-shot algorithm, and after that never empty.
+    buffer: Vec<u8>,
+}
+
+impl Xxh3Hasher {
+    pub(crate) fn boxed() -> Box<dyn AlgorithmHasher> {
+        Box::new(Xxh3Hasher {
+            acc: XXH3_INITIAL_ACC,
+            stripes: 0,
+            len: 0,
+            last_stripe: [0; 64],
+            buffer: Vec::new(),
+        })
+    }
+
+    pub(crate) fn restore(state: &[u8]) -> Option<Box<dyn AlgorithmHasher>> {
+        let state = versioned(state)?;
+        if state.len() < 137 {
+            return None;
+        }
+        let (acc, rest) = state.split_at(64);
+        let (stripes, rest) = rest.split_at(1);
+        let (len, rest) = rest.split_at(8);
+        let (last_stripe, buffer) = rest.split_at(64);
+        let len = u64::from_be_bytes(len.try_into().ok()?);
+        let stripes = stripes[0];
+        let buffered = buffer.len() as u64;
+        let consistent = if len <= XXH3_MID_SIZE_MAX {
+            buffered == len
+        } else {
+            (1..=64).contains(&buffered)
+        };
+        if !consistent || stripes >= XXH3_STRIPES_PER_BLOCK {
+            return None;
+        }
+        let mut hasher = Xxh3Hasher {
+            acc: [0; 8],
+            stripes,
+            len,
+            last_stripe: last_stripe.try_into().ok()?,
+            buffer: buffer.to_vec(),
+        };
+        for (word, bytes) in hasher.acc.iter_mut().zip(acc.chunks_exact(8)) {
+            *word = u64::from_be_bytes(bytes.try_into().ok()?);
+        }
+        Some(Box::new(hasher))
+    }
+
+    fn accumulate(acc: &mut [u64; 8], stripe: &[u8], secret: &[u8]) {
+        for i in 0..8 {
+            let data = read_u64_le(stripe, i * 8);
+            let key = data ^ read_u64_le(secret, i * 8);
+            acc[i ^ 1] = acc[i ^ 1].wrapping_add(data);
+            acc[i] = acc[i].wrapping_add((key & 0xFFFF_FFFF).wrapping_mul(key >> 32));
+        }
+    }
+
+    /// Accumulates one stripe that's known not to be the final one.
+    fn consume(&mut self, stripe: &[u8]) {
+        let secret = &XXH3_SECRET[self.stripes as usize * 8..];
+        Self::accumulate(&mut self.acc, stripe, secret);
+        self.stripes += 1;
+        if self.stripes == XXH3_STRIPES_PER_BLOCK {
+            self.scramble();
+            self.stripes = 0;
+        }
+    }
+
+    fn scramble(&mut self) {
+        let secret = &XXH3_SECRET[192 - 64..];
+        for (i, acc) in self.acc.iter_mut().enumerate() {
+            let mut value = *acc ^ (*acc >> 47);
+            value ^= read_u64_le(secret, i * 8);
+            *acc = value.wrapping_mul(XXH_PRIME32_1);
+        }
+    }
+
+    fn merge(acc: &[u64; 8], secret: &[u8], start: u64) -> u64 {
+        let mut result = start;
+        for i in 0..4 {
+            let low = acc[i * 2] ^ read_u64_le(secret, i * 16);
+            let high = acc[i * 2 + 1] ^ read_u64_le(secret, i * 16 + 8);
+            let product = low as u128 * high as u128;
+            result = result.wrapping_add(product as u64 ^ (product >> 64) as u64);
+        }
+        result ^= result >> 37;
+        result = result.wrapping_mul(0x165667919E3779F9);
+        result ^ (result >> 32)
+    }
+}
+
+fn read_u64_le(data: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(data[offset..offset + 8].try_into().unwrap())
+}
+
+impl AlgorithmHasher for Xxh3Hasher {
+    fn update(&mut self, mut data: &[u8]) {
+        if data.is_empty() {
+            return;
+        }
+        self.len += data.len() as u64;
+        if self.len <= XXH3_MID_SIZE_MAX {
+            self.buffer.extend_from_slice(data);
+            return;
+        }
+        // A stripe is only accumulated once there's input after it, since
+        // the final stripe is handled differently. Only what's left of the
+        // buffer gets copied; full stripes are read straight from `data`.
+        let mut buffer = mem::take(&mut self.buffer);
+        let mut offset = 0;
+        while buffer.len() - offset >= 64 {
+            self.consume(&buffer[offset..offset + 64]);
+            offset += 64;
+        }
+        if offset > 0 {
+            self.last_stripe
+                .copy_from_slice(&buffer[offset - 64..offset]);
+        }
+        let rest = buffer.len() - offset;
+        if rest + data.len() <= 64 {
+            buffer.drain(..offset);
+            buffer.extend_from_slice(data);
+            self.buffer = buffer;
+            return;
+        }
+        if rest > 0 {
+            let mut stripe = [0; 64];
+            stripe[..rest].copy_from_slice(&buffer[offset..]);
+            stripe[rest..].copy_from_slice(&data[..64 - rest]);
+            self.consume(&stripe);
+            self.last_stripe = stripe;
+            data = &data[64 - rest..];
+        }
+        let mut consumed = 0;
+        while data.len() - consumed > 64 {
+            self.consume(&data[consumed..consumed + 64]);
+            consumed += 64;
+        }
+        if consumed > 0 {
+            self.last_stripe
+                .copy_from_slice(&data[consumed - 64..consumed]);
+        }
+        buffer.clear();
+        buffer.extend_from_slice(&data[consumed..]);
+        self.buffer = buffer;
+    }
+
+    fn finalize(self: Box<Self>) -> Vec<u8> {
+        if self.len <= XXH3_MID_SIZE_MAX {
+            return xxhash_rust::xxh3::xxh3_128(&self.buffer)
+                .to_be_bytes()
+                .to_vec();
+        }
+        let mut stripe = [0; 64];
+        let split = 64 - self.buffer.len();
+        stripe[..split].copy_from_slice(&self.last_stripe[64 - split..]);
+        stripe[split..].copy_from_slice(&self.buffer);
+        let mut acc = self.acc;
+        Self::accumulate(&mut acc, &stripe, &XXH3_SECRET[192 - 64 - 7..]);
+        let low = Self::merge(
+            &acc,
+            &XXH3_SECRET[11..],
+            self.len.wrapping_mul(XXH_PRIME64_1),
+        );
+        let high = Self::merge(
+            &acc,
+            &XXH3_SECRET[192 - 64 - 11..],
+            !self.len.wrapping_mul(XXH_PRIME64_2),
+        );
+        (((high as u128) << 64) | low as u128)
+            .to_be_bytes()
+            .to_vec()
+    }
+
+    fn box_clone(&self) -> Box<dyn AlgorithmHasher> {
+        Box::new(self.clone())
+    }
+
+    fn export_state(&self) -> Option<Vec<u8>> {
+        let mut out = vec![STATE_VERSION];
+        for word in &self.acc {
+            out.extend_from_slice(&word.to_be_bytes());
+        }
+        out.push(self.stripes);
+        out.extend_from_slice(&self.len.to_be_bytes());
+        out.extend_from_slice(&self.last_stripe);
+        out.extend_from_slice(&self.buffer);
+        Some(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use digest::Digest;
+
+    use super::{Sha256Hasher, Sha512Hasher, Xxh3Hasher};
+    use crate::registry::AlgorithmHasher;
+
+    fn data(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i * 31 % 251) as u8).collect()
+    }
+
+    /// Hashes `data` in uneven chunks, exporting and restoring the state
+    /// between each one.
+    fn resumed(
+        new: fn() -> Box<dyn AlgorithmHasher>,
+        restore: fn(&[u8]) -> Option<Box<dyn AlgorithmHasher>>,
+        data: &[u8],
+    ) -> Vec<u8> {
+        let mut hasher = new();
+        for chunk in data.chunks(97) {
+            hasher.update(chunk);
+            hasher = restore(&hasher.export_state().unwrap()).unwrap();
+        }
+        hasher.finalize()
+    }
+
+    const LENGTHS: &[usize] = &[
+        0, 1, 55, 56, 63, 64, 65, 111, 112, 127, 128, 129, 240, 241, 1024, 1025, 1088, 4096, 10000,
+    ];
+
+    #[test]
+    fn sha256() {
+        for &len in LENGTHS {
+            let data = data(len);
+            let expected = sha2::Sha256::digest(&data).to_vec();
+            let mut hasher = Sha256Hasher::boxed();
+            hasher.update(&data);
+            assert_eq!(hasher.finalize(), expected, "length {}", len);
+            let actual = resumed(Sha256Hasher::boxed, Sha256Hasher::restore, &data);
+            assert_eq!(actual, expected, "resumed length {}", len);
+        }
+    }
+
+    #[test]
+    fn sha384_512() {
+        for &len in LENGTHS {
+            let data = data(len);
+            let expected = sha2::Sha384::digest(&data).to_vec();
+            let actual = resumed(Sha512Hasher::boxed_384, Sha512Hasher::restore_384, &data);
+            assert_eq!(actual, expected, "length {}", len);
+            let expected = sha2::Sha512::digest(&data).to_vec();
+            let actual = resumed(Sha512Hasher::boxed_512, Sha512Hasher::restore_512, &data);
+            assert_eq!(actual, expected, "length {}", len);
+        }
+    }
+
+    #[test]
+    fn xxh3() {
+        for &len in LENGTHS {
+            let data = data(len);
+            let expected = xxhash_rust::xxh3::xxh3_128(&data).to_be_bytes().to_vec();
+            let mut hasher = Xxh3Hasher::boxed();
+            hasher.update(&data);
+            assert_eq!(hasher.finalize(), expected, "length {}", len);
+            for chunk in [1, 7, 63, 64, 65, 200, 300, 4096] {
+                let mut hasher = Xxh3Hasher::boxed();
+                for piece in data.chunks(chunk) {
+                    hasher.update(piece);
+                }
+                assert_eq!(hasher.finalize(), expected, "length {} by {}", len, chunk);
+            }
+            let actual = resumed(Xxh3Hasher::boxed, Xxh3Hasher::restore, &data);
+            assert_eq!(actual, expected, "resumed length {}", len);
+        }
+    }
+
+    #[test]
+    fn rejects_bad_state() {
+        let mut hasher = Sha256Hasher::boxed();
+        hasher.update(b"hello");
+        let mut state = hasher.export_state().unwrap();
+        assert!(Sha256Hasher::restore(&state).is_some());
+        state.push(0);
+        assert!(Sha256Hasher::restore(&state).is_none());
+        state.pop();
+        state[0] = 2;
+        assert!(Sha256Hasher::restore(&state).is_none());
+        assert!(Sha512Hasher::restore_512(&[1; 10]).is_none());
+        assert!(Xxh3Hasher::restore(&[]).is_none());
+    }
+}
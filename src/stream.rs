@@ -0,0 +1,159 @@
+use crate::algorithm::Algorithm;
+use crate::checker;
+use crate::errors::Error;
+use crate::integrity::Integrity;
+use crate::opts::IntegrityOpts;
+
+/// Shared state for the streaming adapters: generates an `Integrity` for
+/// everything passed to `input()`, and optionally checks it against an
+/// expected one when finished.
+#[derive(Debug)]
+pub(crate) struct StreamChecker {
+    opts: Option<IntegrityOpts>,
+    expected: Option<Integrity>,
+    expected_len: Option<u64>,
+    bytes: u64,
+    started: bool,
+    result: Option<Result<(Option<Algorithm>, Integrity), Error>>,
+}
+
+impl StreamChecker {
+    pub(crate) fn new(opts: IntegrityOpts) -> StreamChecker {
+        StreamChecker {
+            opts: Some(opts),
+            expected: None,
+            expected_len: None,
+            bytes: 0,
+            started: false,
+            result: None,
+        }
+    }
+
+    /// Check the data against `sri` when finished, adding the algorithm it
+    /// prefers if it's not already being generated. An empty `sri` can't
+    /// match anything, so the check fails when finished.
+    ///
+    /// Panics once anything has been input, or the checker has finished,
+    /// whether or not an algorithm would need to be added.
+    pub(crate) fn expect(&mut self, sri: Integrity) {
+        assert!(
+            !self.started,
+            "Can't set the expected Integrity once input has started"
+        );
+        if let Some(algo) = sri.hashes.first().map(|h| h.algorithm) {
+            if let Some(opts) = self.opts.take() {
+                self.opts = Some(
+                    if matches!(algo, Algorithm::Unknown(_)) || opts.has_algorithm(algo) {
+                        opts
+                    } else {
+                        opts.algorithm(algo)
+                    },
+                );
+            }
+        }
+        self.expected = Some(sri);
+    }
+
+    /// Fail as soon as more than `len` bytes are input, and when finished if
+    /// fewer were.
+    pub(crate) fn expect_len(&mut self, len: u64) {
+        self.expected_len = Some(len);
+    }
+
+    /// Hashes `data`. If this goes over the expected length, nothing more is
+    /// hashed and the checker finishes with an error right away.
+    pub(crate) fn input(&mut self, data: &[u8]) -> Result<(), Error> {
+        self.started = true;
+        if self.is_finished() {
+            return self.finish();
+        }
+        let bytes = self.bytes + data.len() as u64;
+        if let Some(expected) = self.expected_len {
+            if bytes > expected {
+                self.bytes = bytes;
+                return self.overflow(expected, bytes);
+            }
+        }
+        if let Some(opts) = self.opts.as_mut() {
+            opts.input(data);
+            self.bytes = bytes;
+        }
+        Ok(())
+    }
+
+    fn overflow(&mut self, expected: u64, bytes: u64) -> Result<(), Error> {
+        self.opts = None;
+        self.result = Some(Err(Error::TooManyBytes(expected, bytes)));
+        self.finish()
+    }
+
+    /// Truncates `buf` to the bytes that still fit in the expected length, so
+    /// writers never pass on more than that. Fails if nothing fits.
+    pub(crate) fn limit<'a>(&mut self, buf: &'a [u8]) -> Result<&'a [u8], Error> {
+        match self.expected_len {
+            Some(expected) if !buf.is_empty() => {
+                let remaining = expected.saturating_sub(self.bytes);
+                if remaining == 0 {
+                    self.overflow(expected, self.bytes + buf.len() as u64)?;
+                }
+                Ok(&buf[..buf.len().min(remaining as usize)])
+            }
+            _ => Ok(buf),
+        }
+    }
+
+    pub(crate) fn bytes(&self) -> u64 {
+        self.bytes
+    }
+
+    pub(crate) fn is_finished(&self) -> bool {
+        self.result.is_some()
+    }
+
+    /// Generates the `Integrity` and checks it, if needed. Calling this again
+    /// returns the same result.
+    pub(crate) fn finish(&mut self) -> Result<(), Error> {
+        self.started = true;
+        if self.result.is_none() {
+            let sri = self.opts.take().unwrap_or_default().result();
+            self.result = Some(match (self.expected.take(), self.expected_len) {
+                (_, Some(len)) if self.bytes < len => Err(Error::TooFewBytes(len, self.bytes)),
+                (Some(expected), _) => {
+                    checker::verify(expected, sri).map(|(a, sri)| (Some(a), sri))
+                }
+                (None, _) => Ok((None, sri)),
+            });
+        }
+        match self.result.as_ref() {
+            Some(Err(e)) => Err(e.clone()),
+            _ => Ok(()),
+        }
+    }
+
+    /// The generated `Integrity`, once finished. This is available even if
+    /// the check failed.
+    pub(crate) fn integrity(&self) -> Option<&Integrity> {
+        match self.result.as_ref()? {
+            Ok((_, sri)) => Some(sri),
+            Err(Error::IntegrityCheckError(_, sri)) => Some(sri),
+            Err(_) => None,
+        }
+    }
+
+    /// The algorithm the data was checked with, once finished, if the check
+    /// passed.
+    pub(crate) fn algorithm(&self) -> Option<Algorithm> {
+        match self.result.as_ref()? {
+            Ok((algo, _)) => *algo,
+            Err(_) => None,
+        }
+    }
+
+    pub(crate) fn into_result(mut self) -> Result<Integrity, Error> {
+        self.finish()?;
+        match self.result {
+            Some(Ok((_, sri))) => Ok(sri),
+            _ => unreachable!("finish() always stores a result"),
+        }
+    }
+}
@@ -0,0 +1,188 @@
+use std::io::{self, Write};
+
+use crate::errors::Error;
+use crate::integrity::Integrity;
+use crate::opts::IntegrityOpts;
+use crate::reader::invalid_data;
+use crate::stream::StreamChecker;
+
+/**
+Wraps a [`Write`](std::io::Write), forwarding everything written to it while
+generating an [`Integrity`](struct.Integrity.html) for the data, and
+optionally checking it against an expected one.
+
+Only the bytes the inner writer actually accepts are hashed.
+
+# Examples
+
+```
+# use ssri::{Algorithm, Integrity, IntegrityOpts, IntegrityWriter};
+use std::io::Write;
+
+let expected = Integrity::from(b"hello world");
+let opts = IntegrityOpts::new().algorithm(Algorithm::Sha512);
+let mut writer = IntegrityWriter::new(Vec::new(), opts).expected(expected);
+writer.write_all(b"hello world").unwrap();
+let (sri, data) = writer.finish().unwrap();
+assert_eq!(data, b"hello world");
+assert_eq!(sri.hashes.len(), 2);
+```
+*/
+#[derive(Debug)]
+pub struct IntegrityWriter<W> {
+    inner: W,
+    checker: StreamChecker,
+}
+
+impl<W: Write> IntegrityWriter<W> {
+    /// Creates a new `IntegrityWriter` that forwards data to `inner`, hashing
+    /// it with the algorithms configured on `opts`.
+    pub fn new(inner: W, opts: IntegrityOpts) -> IntegrityWriter<W> {
+        IntegrityWriter {
+            inner,
+            checker: StreamChecker::new(opts),
+        }
+    }
+
+    /// Makes [`IntegrityWriter::finish`] check the written data against
+    /// `sri`, the same way [`IntegrityChecker`](struct.IntegrityChecker.html)
+    /// would. If it's not already configured, the algorithm `sri` prefers is
+    /// added to the generated `Integrity`.
+    ///
+    /// Panics if called after the first successful write, even if it was empty.
+    pub fn expected(mut self, sri: Integrity) -> Self {
+        self.checker.expect(sri);
+        self
+    }
+
+    /// Refuses to write more than `len` bytes: a write that would go over is
+    /// cut short, and once `len` bytes have been written, further writes fail
+    /// with [`Error::TooManyBytes`](enum.Error.html#variant.TooManyBytes).
+    /// [`IntegrityWriter::finish`] fails with
+    /// [`Error::TooFewBytes`](enum.Error.html#variant.TooFewBytes) if fewer
+    /// were written.
+    pub fn expected_len(mut self, len: u64) -> Self {
+        self.checker.expect_len(len);
+        self
+    }
+
+    /// Number of bytes written through this writer so far.
+    pub fn bytes_written(&self) -> u64 {
+        self.checker.bytes()
+    }
+
+    /// Generates the `Integrity` for everything written so far, checking it
+    /// if an expected `Integrity` was provided, and returns it along with the
+    /// inner writer.
+    ///
+    /// This doesn't flush the inner writer.
+    pub fn finish(self) -> Result<(Integrity, W), Error> {
+        Ok((self.checker.into_result()?, self.inner))
+    }
+}
+
+impl<W: Write> Write for IntegrityWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let buf = self.checker.limit(buf).map_err(invalid_data)?;
+        let written = self.inner.write(buf)?;
+        self.checker.input(&buf[..written]).map_err(invalid_data)?;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::IntegrityWriter;
+    use crate::{Algorithm, Error, Integrity, IntegrityOpts};
+
+    #[test]
+    fn tee() {
+        let opts = IntegrityOpts::new()
+            .algorithm(Algorithm::Sha1)
+            .algorithm(Algorithm::Sha256);
+        let mut writer = IntegrityWriter::new(Vec::new(), opts);
+        writer.write_all(b"hello ").unwrap();
+        writer.write_all(b"world").unwrap();
+        assert_eq!(writer.bytes_written(), 11);
+        let (sri, data) = writer.finish().unwrap();
+        assert_eq!(data, b"hello world");
+        assert_eq!(
+            sri.to_string(),
+            "sha256-uU0nuZNNPgilLlLX2n2r+sSE7+N6U4DukIj3rOLvzek= sha1-Kq5sNclPz7QV2+lfQIuc6R7oRu0="
+        );
+    }
+
+    #[test]
+    fn expected() {
+        let expected = Integrity::from(b"hello world");
+        let mut writer = IntegrityWriter::new(Vec::new(), IntegrityOpts::new()).expected(expected);
+        writer.write_all(b"hello world").unwrap();
+        let (sri, _) = writer.finish().unwrap();
+        assert_eq!(sri, Integrity::from(b"hello world"));
+    }
+
+    #[test]
+    fn mismatch() {
+        let expected = Integrity::from(b"hello world");
+        let opts = IntegrityOpts::new().algorithm(Algorithm::Sha1);
+        let mut writer = IntegrityWriter::new(Vec::new(), opts).expected(expected.clone());
+        writer.write_all(b"goodbye world").unwrap();
+        let err = writer.finish().unwrap_err();
+        match err {
+            Error::IntegrityCheckError(wanted, actual) => {
+                assert_eq!(wanted, expected);
+                assert_eq!(actual.pick_algorithm(), Algorithm::Sha256);
+                assert_eq!(actual.hashes.len(), 2);
+            }
+            _ => panic!("unexpected error: {}", err),
+        }
+    }
+
+    #[test]
+    fn expected_empty() {
+        let empty = Integrity { hashes: vec![] };
+        let opts = IntegrityOpts::new().algorithm(Algorithm::Sha1);
+        let mut writer = IntegrityWriter::new(Vec::new(), opts).expected(empty.clone());
+        writer.write_all(b"hello world").unwrap();
+        assert_eq!(
+            writer.finish().unwrap_err(),
+            Error::IntegrityCheckError(empty, "sha1-Kq5sNclPz7QV2+lfQIuc6R7oRu0=".parse().unwrap())
+        );
+    }
+
+    #[test]
+    fn expected_len() {
+        let mut writer = IntegrityWriter::new(Vec::new(), IntegrityOpts::new()).expected_len(5);
+        assert_eq!(writer.write(b"hello world").unwrap(), 5);
+        let err = writer.write(b" world").unwrap_err();
+        let inner = err.into_inner().unwrap().downcast::<Error>().unwrap();
+        assert_eq!(*inner, Error::TooManyBytes(5, 11));
+        assert!(writer.finish().is_err());
+
+        let opts = IntegrityOpts::new().algorithm(Algorithm::Sha256);
+        let mut writer = IntegrityWriter::new(Vec::new(), opts).expected_len(5);
+        writer.write_all(b"hello").unwrap();
+        let (sri, data) = writer.finish().unwrap();
+        assert_eq!(data, b"hello");
+        assert_eq!(sri, Integrity::from(b"hello"));
+
+        let mut writer = IntegrityWriter::new(Vec::new(), IntegrityOpts::new()).expected_len(5);
+        writer.write_all(b"hell").unwrap();
+        assert_eq!(writer.finish().unwrap_err(), Error::TooFewBytes(5, 4));
+    }
+
+    #[test]
+    #[should_panic(expected = "input has started")]
+    fn expected_after_write() {
+        let opts = IntegrityOpts::new().algorithm(Algorithm::Sha256);
+        let mut writer = IntegrityWriter::new(Vec::new(), opts);
+        writer.write_all(b"hello").unwrap();
+        let _ = writer.expected(Integrity::from(b"hello"));
+    }
+}